@@ -1,9 +1,10 @@
-use std::{collections::HashMap, io::{self, Cursor, BufReader, BufRead}, fs, path::{Path}, ops::DerefMut};
+use std::{collections::HashMap, io::{self, Cursor, BufReader, BufRead}, fs, path::{Path, PathBuf}, ops::DerefMut};
 
 use indexmap::IndexMap;
+use serde::{Serialize, Deserialize};
 use wgpu::RenderPipeline;
 
-use crate::graphics::{Mesh, GraphicsBundle, Texture, Material};
+use crate::{graphics::{Mesh, GraphicsBundle, Texture, TextureSettings, Material, EnvironmentMaps, Skybox}, scene::{Prefab, SceneFormat}, components::EnvironmentLight};
 
 
 
@@ -13,7 +14,11 @@ pub enum Error {
     FileTooLarge,
     WGPUUnknown,
     NullReference,
-    AssetNotFound
+    AssetNotFound,
+    FileWriteFailed,
+    FileReadFailed(String),
+    InvalidFormat(String),
+    UnknownComponent(String),
 }
 
 impl std::fmt::Debug for Error {
@@ -24,6 +29,10 @@ impl std::fmt::Debug for Error {
             Self::WGPUUnknown => write!(f, "AssetManager : Unknown error occured within WGPU"),
             Self::NullReference => write!(f, "AssetManager : AssetRef does not contain a name OR id"),
             Self::AssetNotFound => write!(f, "AssetManager : Asset not registered"),
+            Self::FileWriteFailed => write!(f, "AssetManager : Could not write file"),
+            Self::FileReadFailed(err) => write!(f, "AssetManager : Could not read file, {}", err),
+            Self::InvalidFormat(err) => write!(f, "AssetManager : Could not parse file, {}", err),
+            Self::UnknownComponent(name) => write!(f, "AssetManager : Component '{}' is not registered in the SceneRegistry", name),
         }
     }
 }
@@ -36,6 +45,7 @@ pub struct AssetManager {
     pub materials: IndexMap<String, Material>,
     render_pipelines: IndexMap<String, wgpu::RenderPipeline>,
     shaders: IndexMap<String, wgpu::ShaderModule>,
+    prefabs: IndexMap<String, Prefab>,
 }
 
 impl AssetManager {
@@ -45,32 +55,43 @@ impl AssetManager {
         let materials = IndexMap::new();
         let render_pipelines = IndexMap::new();
         let shaders = IndexMap::new();
-        AssetManager { meshes, textures, materials, render_pipelines, shaders }
+        let prefabs = IndexMap::new();
+        AssetManager { meshes, textures, materials, render_pipelines, shaders, prefabs }
+    }
+
+    /// Path of `file_name` in the asset folder, the copy of `res/` made by the build script.
+    fn asset_path(file_name: &str) -> PathBuf {
+        Path::new(env!("OUT_DIR"))
+            .join("res")
+            .join(file_name)
     }
 
     /// loads a file as bytes
     pub async fn load_bytes(file_name: &str) -> Result<Vec<u8>, io::Error> {
-        let path = Path::new(env!("OUT_DIR"))
-            .join("res")
-            .join(file_name);
-        fs::read(path)
+        fs::read(AssetManager::asset_path(file_name))
     }
     /// loads a file as a String
     pub async fn load_string(file_name: &str) -> Result<String, io::Error> {
-        let path = Path::new(env!("OUT_DIR"))
-            .join("res")
-            .join(file_name);
-        fs::read_to_string(path)
+        fs::read_to_string(AssetManager::asset_path(file_name))
     }
 
     pub async fn load_texture(&mut self, bundle: &GraphicsBundle<'_>, file_name: &str) -> Result<AssetRef, Error> {
+        self.load_texture_with_settings(bundle, file_name, &TextureSettings::default()).await
+    }
+
+    /// Loads a texture holding data rather than color, e.g. a normal or metallic/roughness map.
+    pub async fn load_linear_texture(&mut self, bundle: &GraphicsBundle<'_>, file_name: &str) -> Result<AssetRef, Error> {
+        self.load_texture_with_settings(bundle, file_name, &TextureSettings::linear()).await
+    }
+
+    pub async fn load_texture_with_settings(&mut self, bundle: &GraphicsBundle<'_>, file_name: &str, settings: &TextureSettings) -> Result<AssetRef, Error> {
         let bytes: Vec<u8>;
         match AssetManager::load_bytes(file_name).await {
             Ok(res) => {bytes = res},
             Err(err) => {return Err(Error::FileNotFound)}
         };
         let texture: Texture;
-        match Texture::from_bytes(bundle, &bytes, file_name) {
+        match Texture::from_bytes_with_settings(bundle, &bytes, file_name, settings) {
             Ok(res) => {texture = res},
             Err(err) => {return Err(Error::WGPUUnknown)},
         };
@@ -89,6 +110,76 @@ impl AssetManager {
 
     }
 
+    /// Loads six square images of the same size into a cube map registered as `name`,
+    /// in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub async fn load_cubemap(&mut self, bundle: &GraphicsBundle<'_>, file_names: [&str; 6], name: &str) -> Result<AssetRef, Error> {
+        let mut faces = Vec::with_capacity(6);
+        for file_name in file_names {
+            let bytes = AssetManager::load_bytes(file_name).await.map_err(|_| Error::FileNotFound)?;
+            let face = image::load_from_memory(&bytes).map_err(|err| Error::InvalidFormat(err.to_string()))?;
+            faces.push(face);
+        }
+        let size = faces[0].width();
+        if faces.iter().any(|face| face.width() != size || face.height() != size) {
+            return Err(Error::InvalidFormat(String::from("cube map faces have to be square and the same size")));
+        }
+        let texture = Texture::cubemap_from_images(bundle, &faces, name);
+        Ok(self.insert_cubemap(bundle, texture, name))
+    }
+
+    /// Loads an equirectangular image, usually an `.hdr` one, and converts it into a cube map
+    /// with `size` texels per side, registered under its file stem.
+    pub async fn load_equirectangular(&mut self, bundle: &GraphicsBundle<'_>, file_name: &str, size: u32) -> Result<AssetRef, Error> {
+        let bytes = AssetManager::load_bytes(file_name).await.map_err(|_| Error::FileNotFound)?;
+        let image = image::load_from_memory(&bytes).map_err(|err| Error::InvalidFormat(err.to_string()))?;
+        let name = Path::new(&file_name).file_stem().and_then(|s| s.to_str())
+            .ok_or_else(|| Error::InvalidFormat(format!("no file name in \"{}\"", file_name)))?;
+        let texture = Texture::cubemap_from_equirectangular(bundle, &image, size, name);
+        Ok(self.insert_cubemap(bundle, texture, name))
+    }
+
+    /// Loads an environment like `load_equirectangular` and bakes its image based lighting on the GPU,
+    /// the maps are registered as "<stem> Irradiance" and "<stem> Specular".
+    /// Insert the returned light into the world to light the scene with it.
+    pub async fn load_environment(&mut self, bundle: &GraphicsBundle<'_>, file_name: &str, size: u32) -> Result<EnvironmentLight, Error> {
+        let skybox = self.load_equirectangular(bundle, file_name, size).await?;
+        let name = skybox.name.clone().unwrap_or_default();
+        let maps = EnvironmentMaps::from_cubemap(bundle, self.get_texture(skybox.clone())?, size, &name);
+        let irradiance = self.insert_texture(maps.irradiance, &format!("{} Irradiance", name));
+        let specular = self.insert_texture(maps.specular, &format!("{} Specular", name));
+        Ok(EnvironmentLight::new(skybox, irradiance, specular).with_source(file_name, size))
+    }
+
+    /// Registers a cube map along with the bind group drawing it as a skybox.
+    fn insert_cubemap(&mut self, bundle: &GraphicsBundle<'_>, mut texture: Texture, name: &str) -> AssetRef {
+        texture.bind_group = Some(Skybox::bind_group(bundle.device, &texture));
+        self.insert_texture(texture, name)
+    }
+
+    fn insert_texture(&mut self, texture: Texture, name: &str) -> AssetRef {
+        let (id, _) = self.textures.insert_full(String::from(name), texture);
+        AssetRef {
+            id: Some(id),
+            name: Some(String::from(name)),
+            asset_type: AssetType::Texture,
+        }
+    }
+
+    /// Loads a prefab file (RON, or JSON with a `.json` extension) and registers it under its file stem.
+    pub async fn load_prefab(&mut self, file_name: &str) -> Result<AssetRef, Error> {
+        let text = AssetManager::load_string(file_name).await.map_err(|_| Error::FileNotFound)?;
+        let prefab = Prefab::parse(&text, SceneFormat::from_path(file_name))?;
+
+        let name = Path::new(&file_name).file_stem().and_then(|s| s.to_str())
+            .ok_or_else(|| Error::InvalidFormat(format!("no file name in \"{}\"", file_name)))?;
+        let id = prefab.insert(self, name);
+        Ok(AssetRef {
+            id: Some(id),
+            name: Some(String::from(name)),
+            asset_type: AssetType::Prefab,
+        })
+    }
+
     pub fn get_texture (&self, target: AssetRef) -> Result<&Texture, Error> {
         let storage = &self.textures;
         if target.id.is_some() {
@@ -159,6 +250,16 @@ impl AssetManager {
         return Err(Error::NullReference);
     }
 
+    pub fn get_prefab(&self, target: AssetRef) -> Result<&Prefab, Error> {
+        if let Some((_, prefab)) = target.id.and_then(|id| self.prefabs.get_index(id)) {
+            return Ok(prefab);
+        }
+        match target.name {
+            Some(name) => self.prefabs.get(&name).ok_or(Error::AssetNotFound),
+            None => Err(Error::NullReference),
+        }
+    }
+
     pub fn insert_asset<T: Asset>(&mut self, asset: T, asset_type: AssetType, name: &str) -> AssetRef {
         let id = asset.insert(self, name);
         
@@ -216,9 +317,9 @@ impl AssetManager {
     */
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum AssetType {
-    Mesh, Texture, Material, Shader, RenderPipeline
+    Mesh, Texture, Material, Shader, RenderPipeline, Prefab
 }
 
 pub trait Asset: Sized {
@@ -250,6 +351,11 @@ impl Asset for Material {
         asset_manager.materials.len()
     } 
 }
+impl Asset for Prefab {
+    fn insert(self, asset_manager: &mut AssetManager, name: &str) -> usize {
+        asset_manager.prefabs.insert_full(name.to_owned(), self).0
+    } 
+}
 impl Asset for RenderPipeline {
     fn insert(self, asset_manager: &mut AssetManager, name: &str) -> usize {
         asset_manager.render_pipelines.insert(name.to_owned(), self);
@@ -258,8 +364,10 @@ impl Asset for RenderPipeline {
 }
 
 
-#[derive(Debug)]
+/// Scene files only store the name, ids depend on load order.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AssetRef {
+    #[serde(skip, default)]
     pub id: Option<usize>,
     pub name: Option<String>,
     pub asset_type: AssetType,
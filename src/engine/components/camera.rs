@@ -1,10 +1,12 @@
 use std::f32::consts::FRAC_PI_2;
 
 use glam::{Mat4, Vec3, Vec4, Quat, EulerRot};
+use serde::{Serialize, Deserialize};
 use wgpu::util::DeviceExt;
 
 use crate::{
-    ecs::{ Component, VecStorage }, components::{Transform}, graphics::GraphicsBundle
+    ecs::{ Component, VecStorage }, components::{Transform}, graphics::{GraphicsBundle, Color},
+    assets::asset_manager::{Error, AssetRef}, scene::{SceneComponent, SceneContext}, spatial::Frustum,
 };
 
 use super::transform::{Position, Rotation};
@@ -13,14 +15,211 @@ use super::transform::{Position, Rotation};
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
 pub struct Camera {
+    /// aspect ratio of the whole target, the viewport's is `viewport_aspect`
     pub aspect: f32,
+    /// size in pixels of the whole target
+    pub target_size: (u32, u32),
+    pub projection: Projection,
+    /// vertical field of view of the perspective projection
     pub fovy: f32,
     pub znear: f32,
     pub zfar: f32,
     pub uniform: CameraUniform,
     pub buffer: wgpu::Buffer,
     pub bind_group: wgpu::BindGroup,
+    /// renders into this texture instead of the surface
+    pub target: Option<RenderTarget>,
+    pub viewport: Viewport,
+    /// cameras drawing into the same target are drawn from the lowest priority up, later ones on top
+    pub priority: i32,
+    pub background: Background,
+    pub clear_flags: ClearFlags,
+    /// inactive cameras aren't drawn
+    pub active: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    /// Parallel projection, `size` is interpreted by `scaling`.
+    Orthographic { size: f32, scaling: OrthographicScaling },
+}
+
+impl Projection {
+    /// Projection into a viewport with `aspect` and `pixels` in size, `fovy` is only used by a perspective one.
+    pub fn matrix(&self, fovy: f32, aspect: f32, pixels: (u32, u32), znear: f32, zfar: f32) -> Mat4 {
+        match self {
+            Projection::Perspective => Camera::OPENGL_TO_WGPU_MATRIX * Mat4::perspective_rh(fovy, aspect, znear, zfar),
+            // glam's orthographic projection already maps depth to wgpu's 0 to 1
+            Projection::Orthographic { .. } => {
+                let (width, height) = self.orthographic_size(aspect, pixels);
+                Mat4::orthographic_rh(-width / 2.0, width / 2.0, -height / 2.0, height / 2.0, znear, zfar)
+            }
+        }
+    }
 
+    /// Width and height in world units covered in a viewport with `aspect` and `pixels` in size, zero for a perspective projection.
+    pub fn orthographic_size(&self, aspect: f32, pixels: (u32, u32)) -> (f32, f32) {
+        let Projection::Orthographic { size, scaling } = *self else {
+            return (0.0, 0.0);
+        };
+        match scaling {
+            OrthographicScaling::FixedHeight => (size * aspect, size),
+            OrthographicScaling::FixedWidth => (size, size / aspect),
+            OrthographicScaling::PixelPerfect => {
+                let size = size.max(f32::EPSILON);
+                (pixels.0.max(1) as f32 / size, pixels.1.max(1) as f32 / size)
+            }
+        }
+    }
+}
+
+/// How the size of an orthographic projection follows the viewport.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrthographicScaling {
+    /// `size` world units from the bottom to the top of the viewport, the width follows the aspect ratio
+    FixedHeight,
+    /// `size` world units from the left to the right of the viewport, the height follows the aspect ratio
+    FixedWidth,
+    /// `size` pixels per world unit, bigger viewports show more of the world.
+    /// The view is snapped to whole pixels so sprites don't shimmer while the camera moves.
+    PixelPerfect,
+}
+
+/// Part of the target a camera draws into, in fractions of its size from the top left corner.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub const FULL: Viewport = Viewport { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Viewport { x, y, width, height }
+    }
+
+    /// Position and size in pixels on a `width` by `height` target, clamped to it.
+    pub fn to_pixels(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let left = (self.x.clamp(0.0, 1.0) * width as f32).round() as u32;
+        let top = (self.y.clamp(0.0, 1.0) * height as f32).round() as u32;
+        let right = ((self.x + self.width).clamp(0.0, 1.0) * width as f32).round() as u32;
+        let bottom = ((self.y + self.height).clamp(0.0, 1.0) * height as f32).round() as u32;
+        (left, top, right.saturating_sub(left), bottom.saturating_sub(top))
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::FULL
+    }
+}
+
+/// What a camera shows where nothing was drawn, when its clear flags clear the color.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Background {
+    Color(Color),
+    /// cube map registered in the asset manager, e.g. by `AssetManager::load_cubemap`
+    Skybox(AssetRef),
+}
+
+impl Background {
+    /// What the viewport is cleared to before the skybox is drawn over it.
+    pub fn clear_color(&self) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Skybox(_) => Color::BLACK,
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(Camera::DEFAULT_BACKGROUND_COLOR)
+    }
+}
+
+/// What a camera clears in its viewport before drawing into it.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClearFlags {
+    /// starts from the clear color
+    #[default]
+    ColorAndDepth,
+    /// draws on top of what the cameras before it drew
+    Depth,
+    /// draws into what the cameras before it drew, hidden behind it where it's closer
+    Nothing,
+}
+
+/// When a camera rendering into a texture draws it again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpdateMode {
+    EveryFrame,
+    /// every `n`th frame
+    Interval(u32),
+    /// the first frame only, again after `RenderTarget::request_update`
+    Once,
+    /// only after `RenderTarget::request_update`
+    Manual,
+}
+
+/// Texture registered in the asset manager a camera renders into, created by `Renderer::create_render_texture`.
+/// Materials show it by using it like any other texture.
+///
+/// The view has no bloom or post-processing, and uses the exposure and shadow cascades of the main camera.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderTarget {
+    pub texture: AssetRef,
+    pub width: u32,
+    pub height: u32,
+    pub update: UpdateMode,
+    #[serde(skip)]
+    update_requested: bool,
+    /// frames since it was last drawn, `None` before the first time
+    #[serde(skip)]
+    frames_since_update: Option<u32>,
+}
+
+impl RenderTarget {
+    pub fn new(texture: AssetRef, width: u32, height: u32) -> Self {
+        RenderTarget { texture, width, height, update: UpdateMode::EveryFrame, update_requested: false, frames_since_update: None }
+    }
+
+    pub fn with_update(mut self, update: UpdateMode) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Draws the texture again on the next frame, whatever the update mode.
+    pub fn request_update(&mut self) {
+        self.update_requested = true;
+    }
+
+    pub fn needs_update(&self) -> bool {
+        if self.update_requested {
+            return true;
+        }
+        match self.update {
+            UpdateMode::EveryFrame => true,
+            UpdateMode::Interval(frames) => self.frames_since_update.map_or(true, |since| since + 1 >= frames),
+            UpdateMode::Once => self.frames_since_update.is_none(),
+            UpdateMode::Manual => false,
+        }
+    }
+
+    /// Called by the renderer once per frame, `drawn` if the texture was drawn in it.
+    pub(crate) fn advance(&mut self, drawn: bool) {
+        if drawn {
+            self.update_requested = false;
+            self.frames_since_update = Some(0);
+        } else if let Some(since) = &mut self.frames_since_update {
+            *since = since.saturating_add(1);
+        }
+    }
 }
 
 impl Camera {
@@ -33,13 +232,15 @@ impl Camera {
     ]);
 
     pub const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
+    pub const DEFAULT_BACKGROUND_COLOR: Color = Color::new(0.2, 0.4, 0.8, 1.0);
 
     pub fn bind_group_layout(bundle: &GraphicsBundle) -> wgpu::BindGroupLayout {
         return bundle.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    // the fragment stage needs the view position for specular lighting
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -85,35 +286,184 @@ impl Camera {
 
         Self {
             aspect: width as f32 / height as f32,
+            target_size: (width, height),
+            projection: Projection::Perspective,
             fovy: fovy.into(),
             znear,
             zfar,
             uniform,
             buffer,
-            bind_group
-
+            bind_group,
+            target: None,
+            viewport: Viewport::FULL,
+            priority: 0,
+            background: Background::default(),
+            clear_flags: ClearFlags::ColorAndDepth,
+            active: true,
         }
     }
 
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Same as `with_background(Background::Color(color))`.
+    pub fn with_clear_color(self, color: Color) -> Self {
+        self.with_background(Background::Color(color))
+    }
+
+    pub fn with_clear_flags(mut self, clear_flags: ClearFlags) -> Self {
+        self.clear_flags = clear_flags;
+        self
+    }
+
+    /// Renders into `target` instead of the surface, the aspect ratio follows the texture's size.
+    pub fn with_target(mut self, target: RenderTarget) -> Self {
+        self.resize(target.width, target.height);
+        self.target = Some(target);
+        self
+    }
+
     
     pub fn calc_matrix(&self, transform: &Transform) -> Mat4 {
         let direction = Quat::mul_vec3(transform.rotation, Vec3::new(0.0, 0.0, 1.0));
-        let transform_matrix = Mat4::look_to_rh(
+        let mut transform_matrix = Mat4::look_to_rh(
             transform.position,
             direction,
             Vec3::new(0.0, 1.0, 0.0),
         );
-        let projection_matrix = Camera::OPENGL_TO_WGPU_MATRIX * Mat4::perspective_rh(self.fovy, self.aspect, self.znear, self.zfar);
-        return projection_matrix * transform_matrix;
+        if let Projection::Orthographic { size, scaling: OrthographicScaling::PixelPerfect } = self.projection {
+            let pixel = 1.0 / size.max(f32::EPSILON);
+            transform_matrix.w_axis.x = (transform_matrix.w_axis.x / pixel).round() * pixel;
+            transform_matrix.w_axis.y = (transform_matrix.w_axis.y / pixel).round() * pixel;
+        }
+        return self.projection_matrix() * transform_matrix;
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        self.projection.matrix(self.fovy, self.viewport_aspect(), self.viewport_pixels(), self.znear, self.zfar)
+    }
+
+    /// Width and height in world units the orthographic projection covers, zero for a perspective one.
+    pub fn orthographic_size(&self) -> (f32, f32) {
+        self.projection.orthographic_size(self.viewport_aspect(), self.viewport_pixels())
+    }
+
+    /// Width and height of the viewport in pixels of the target.
+    fn viewport_pixels(&self) -> (u32, u32) {
+        let (_, _, width, height) = self.viewport.to_pixels(self.target_size.0, self.target_size.1);
+        (width, height)
+    }
+
+    pub fn frustum(&self, transform: &Transform) -> Frustum {
+        Frustum::from_view_proj(&self.calc_matrix(transform))
+    }
+
+    /// World space corners of the view frustum, the near plane's four first.
+    pub fn frustum_corners(&self, transform: &Transform) -> [Vec3; 8] {
+        let inverse = self.calc_matrix(transform).inverse();
+        let mut corners = [Vec3::ZERO; 8];
+        for (index, corner) in corners.iter_mut().enumerate() {
+            let x = if index & 1 == 0 { -1.0 } else { 1.0 };
+            let y = if index & 2 == 0 { -1.0 } else { 1.0 };
+            let z = if index & 4 == 0 { 0.0 } else { 1.0 };
+            *corner = inverse.project_point3(Vec3::new(x, y, z));
+        }
+        corners
+    }
+
+    /// Aspect ratio of the viewport, `aspect` is the one of the whole target.
+    pub fn viewport_aspect(&self) -> f32 {
+        self.aspect * self.viewport.width / self.viewport.height
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.aspect = width as f32 / height as f32;
+        self.target_size = (width, height);
     }
 
     pub fn update_view_proj(&mut self, transform: &Transform) {
         self.uniform.view_position = Vec4::from((transform.position, 0.0)).into();
-        self.uniform.view_proj = self.calc_matrix(transform).to_cols_array_2d();
+        let view_proj = self.calc_matrix(transform);
+        self.uniform.view_proj = view_proj.to_cols_array_2d();
+        self.uniform.inverse_view_proj = view_proj.inverse().to_cols_array_2d();
+    }
+}
+
+/// Aspect ratio and GPU buffers are rebuilt from the current surface on load.
+/// The texture of a target isn't saved, it's looked up by name once it's created.
+#[derive(Serialize, Deserialize)]
+pub struct CameraData {
+    #[serde(default)]
+    pub projection: Projection,
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+    #[serde(default)]
+    pub target: Option<RenderTarget>,
+    #[serde(default)]
+    pub viewport: Viewport,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub background: Background,
+    #[serde(default)]
+    pub clear_flags: ClearFlags,
+    #[serde(default = "default_true")]
+    pub active: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SceneComponent for Camera {
+    type Data = CameraData;
+
+    fn to_scene(&self) -> CameraData {
+        CameraData {
+            projection: self.projection,
+            fovy: self.fovy,
+            znear: self.znear,
+            zfar: self.zfar,
+            target: self.target.clone(),
+            viewport: self.viewport,
+            priority: self.priority,
+            background: self.background.clone(),
+            clear_flags: self.clear_flags,
+            active: self.active,
+        }
+    }
+
+    fn from_scene(data: CameraData, context: &SceneContext) -> Result<Self, Error> {
+        let bundle = context.bundle;
+        let mut camera = Camera::new(bundle, bundle.config.width, bundle.config.height, data.fovy, data.znear, data.zfar)
+            .with_projection(data.projection)
+            .with_viewport(data.viewport)
+            .with_priority(data.priority)
+            .with_background(data.background)
+            .with_clear_flags(data.clear_flags);
+        camera.active = data.active;
+        Ok(match data.target {
+            Some(target) => camera.with_target(target),
+            None => camera,
+        })
     }
 }
 
@@ -122,6 +472,8 @@ impl Camera {
 pub struct CameraUniform {
     pub view_position: [f32; 4],
     pub view_proj: [[f32; 4]; 4],
+    /// for shaders reconstructing positions or view directions from the screen, like the skybox
+    pub inverse_view_proj: [[f32; 4]; 4],
 }
 
 impl CameraUniform {
@@ -129,6 +481,57 @@ impl CameraUniform {
         Self {
             view_position: [0.0; 4],
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            inverse_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZNEAR: f32 = 0.1;
+    const ZFAR: f32 = 100.0;
+
+    fn orthographic(size: f32, scaling: OrthographicScaling, aspect: f32, pixels: (u32, u32)) -> Mat4 {
+        Projection::Orthographic { size, scaling }.matrix(1.0, aspect, pixels, ZNEAR, ZFAR)
+    }
+
+    fn assert_maps(matrix: Mat4, point: Vec3, expected: Vec3) {
+        let projected = matrix.project_point3(point);
+        assert!(projected.abs_diff_eq(expected, 1e-5), "{} projected to {}, expected {}", point, projected, expected);
+    }
+
+    #[test]
+    fn fixed_height_follows_the_aspect_ratio() {
+        let matrix = orthographic(10.0, OrthographicScaling::FixedHeight, 2.0, (800, 400));
+        assert_maps(matrix, Vec3::new(10.0, 5.0, -ZNEAR), Vec3::new(1.0, 1.0, 0.0));
+        assert_maps(matrix, Vec3::new(-10.0, -5.0, -ZFAR), Vec3::new(-1.0, -1.0, 1.0));
+    }
+
+    #[test]
+    fn fixed_width_follows_the_aspect_ratio() {
+        let matrix = orthographic(10.0, OrthographicScaling::FixedWidth, 2.0, (800, 400));
+        assert_maps(matrix, Vec3::new(5.0, 2.5, -ZNEAR), Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn pixel_perfect_shows_more_in_bigger_viewports() {
+        let projection = Projection::Orthographic { size: 32.0, scaling: OrthographicScaling::PixelPerfect };
+        assert_eq!(projection.orthographic_size(2.0, (640, 320)), (20.0, 10.0));
+        assert_eq!(projection.orthographic_size(2.0, (1280, 640)), (40.0, 20.0));
+        assert_maps(orthographic(32.0, OrthographicScaling::PixelPerfect, 2.0, (640, 320)), Vec3::new(10.0, 5.0, -ZNEAR), Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn pixel_perfect_survives_an_empty_viewport() {
+        let projection = Projection::Orthographic { size: 0.0, scaling: OrthographicScaling::PixelPerfect };
+        let (width, height) = projection.orthographic_size(1.0, (0, 0));
+        assert!(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0);
+    }
+
+    #[test]
+    fn perspective_has_no_orthographic_size() {
+        assert_eq!(Projection::Perspective.orthographic_size(2.0, (800, 400)), (0.0, 0.0));
+    }
+}
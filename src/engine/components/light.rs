@@ -1,82 +1,236 @@
+use serde::{Serialize, Deserialize};
 use specs::{Component, VecStorage};
-use wgpu::util::DeviceExt;
 
-use crate::graphics::Color;
+use crate::{graphics::{Color, LightRaw}, assets::asset_manager::{Error, AssetRef}, scene::{SceneComponent, SceneContext}};
 
-use super::{transform::Position, Transform};
+use super::Transform;
 
+/// Directional and spot lights shine along the transform's forward axis.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LightType {
+    /// Light fading out over `range`.
+    Point { range: f32 },
+    /// Light coming from infinitely far away, e.g. the sun.
+    Directional,
+    /// Cone of light, full intensity within `inner_angle` fading out to `outer_angle` (radians from the center).
+    Spot { range: f32, inner_angle: f32, outer_angle: f32 },
+}
 
+impl LightType {
+    pub const POINT: u32 = 0;
+    pub const DIRECTIONAL: u32 = 1;
+    pub const SPOT: u32 = 2;
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct LightUniform {
-    position: [f32; 3],
-    // Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
-    _padding: u32,
-    color: [f32; 3],
-    // Due to uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
-    _padding2: u32,
+    /// Matches the light type constants in `pbr.wgsl`.
+    pub fn id(&self) -> u32 {
+        match self {
+            LightType::Point { .. } => LightType::POINT,
+            LightType::Directional => LightType::DIRECTIONAL,
+            LightType::Spot { .. } => LightType::SPOT,
+        }
+    }
 }
 
-impl LightUniform {
-    pub fn new(color: Color) -> Self {
-        LightUniform { position: [0.0; 3], _padding: 0, color: color.into(), _padding2: 0 }
-    }
-    pub fn update(&mut self, color: Color, transform: &Transform) {
-        self.position = transform.position.into();
-        self.color = color.into();
+impl Default for LightType {
+    fn default() -> Self {
+        LightType::Point { range: 10.0 }
     }
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 #[storage(VecStorage)]
 pub struct Light {
+    pub kind: LightType,
     pub intensity: f32,
     pub color: Color,
-
-    pub uniform: LightUniform,
-    pub buffer: wgpu::Buffer,
-    pub bind_group: wgpu::BindGroup,
+    pub cast_shadows: bool,
+    /// depth offset against shadow acne, in shadow map depth units,
+    /// for point lights a fraction of the range
+    pub shadow_bias: f32,
+    /// size of each face of a point light's shadow cube map, other lights use `GraphicsSettings::shadow_map_size`
+    pub shadow_resolution: u32,
 }
 
 impl Light {
-    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-            label: None,
+    pub const DEFAULT_SHADOW_BIAS: f32 = 0.0005;
+    pub const DEFAULT_SHADOW_RESOLUTION: u32 = 512;
+
+    /// Point light with the default range.
+    pub fn new(color: Color, intensity: f32) -> Self {
+        Light::with_kind(LightType::default(), color, intensity)
+    }
+
+    pub fn with_kind(kind: LightType, color: Color, intensity: f32) -> Self {
+        Light {
+            kind,
+            intensity,
+            color,
+            cast_shadows: false,
+            shadow_bias: Light::DEFAULT_SHADOW_BIAS,
+            shadow_resolution: Light::DEFAULT_SHADOW_RESOLUTION,
+        }
+    }
+
+    pub fn point(color: Color, intensity: f32, range: f32) -> Self {
+        Light::with_kind(LightType::Point { range }, color, intensity)
+    }
+
+    pub fn directional(color: Color, intensity: f32) -> Self {
+        Light::with_kind(LightType::Directional, color, intensity)
+    }
+
+    pub fn spot(color: Color, intensity: f32, range: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        Light::with_kind(LightType::Spot { range, inner_angle, outer_angle }, color, intensity)
+    }
+
+    pub fn with_shadows(mut self) -> Self {
+        self.cast_shadows = true;
+        self
+    }
+
+    pub fn with_shadow_bias(mut self, bias: f32) -> Self {
+        self.shadow_bias = bias;
+        self
+    }
+
+    pub fn with_shadow_resolution(mut self, resolution: u32) -> Self {
+        self.shadow_resolution = resolution;
+        self
+    }
+
+    /// `shadow_index` is the light's first layer in the shadow maps, -1 for none.
+    pub fn to_raw(&self, transform: &Transform, shadow_index: i32, shadow_count: u32) -> LightRaw {
+        let (range, inner_angle, outer_angle) = match self.kind {
+            LightType::Point { range } => (range, 0.0, 0.0),
+            LightType::Directional => (0.0, 0.0, 0.0),
+            LightType::Spot { range, inner_angle, outer_angle } => (range, inner_angle, outer_angle),
+        };
+        LightRaw {
+            position: transform.position.into(),
+            kind: self.kind.id(),
+            direction: transform.forward().into(),
+            range,
+            color: self.color.into(),
+            intensity: self.intensity,
+            inner_cos: inner_angle.cos(),
+            outer_cos: outer_angle.cos(),
+            shadow_index,
+            shadow_bias: self.shadow_bias,
+            shadow_count,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Constant light added to every lit surface, stored as a world resource.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct AmbientLight {
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl AmbientLight {
+    pub fn new(color: Color, intensity: f32) -> Self {
+        AmbientLight { color, intensity }
+    }
+
+    /// Color scaled by intensity, as uploaded to the GPU.
+    pub fn radiance(&self) -> [f32; 3] {
+        let color: [f32; 3] = self.color.into();
+        color.map(|channel| channel * self.intensity)
+    }
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        AmbientLight { color: Color::WHITE, intensity: 0.1 }
+    }
+}
+
+/// Light reflected from the environment maps baked by `AssetManager::load_environment`,
+/// stored as a world resource and used instead of the `AmbientLight` while present.
+///
+/// Scenes only save the names of the maps, `Game::load_scene` bakes them again from `source`
+/// when they aren't registered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentLight {
+    /// cube map the maps were baked from, can be used as a camera's skybox background
+    pub skybox: AssetRef,
+    pub irradiance: AssetRef,
+    pub specular: AssetRef,
+    pub intensity: f32,
+    /// image the maps were loaded from, `None` for maps made some other way
+    #[serde(default)]
+    pub source: Option<EnvironmentSource>,
+}
+
+/// Arguments of the `AssetManager::load_environment` call that loaded an environment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentSource {
+    pub file_name: String,
+    pub size: u32,
+}
+
+impl EnvironmentLight {
+    pub fn new(skybox: AssetRef, irradiance: AssetRef, specular: AssetRef) -> Self {
+        EnvironmentLight { skybox, irradiance, specular, intensity: 1.0, source: None }
+    }
+
+    pub fn with_source(mut self, file_name: &str, size: u32) -> Self {
+        self.source = Some(EnvironmentSource { file_name: String::from(file_name), size });
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LightData {
+    #[serde(default)]
+    pub kind: LightType,
+    pub color: Color,
+    pub intensity: f32,
+    #[serde(default)]
+    pub cast_shadows: bool,
+    #[serde(default = "default_shadow_bias")]
+    pub shadow_bias: f32,
+    #[serde(default = "default_shadow_resolution")]
+    pub shadow_resolution: u32,
+}
+
+fn default_shadow_bias() -> f32 {
+    Light::DEFAULT_SHADOW_BIAS
+}
+
+fn default_shadow_resolution() -> u32 {
+    Light::DEFAULT_SHADOW_RESOLUTION
+}
+
+impl SceneComponent for Light {
+    type Data = LightData;
+
+    fn to_scene(&self) -> LightData {
+        LightData {
+            kind: self.kind,
+            color: self.color,
+            intensity: self.intensity,
+            cast_shadows: self.cast_shadows,
+            shadow_bias: self.shadow_bias,
+            shadow_resolution: self.shadow_resolution,
+        }
+    }
+
+    fn from_scene(data: LightData, context: &SceneContext) -> Result<Self, Error> {
+        Ok(Light {
+            kind: data.kind,
+            color: data.color,
+            intensity: data.intensity,
+            cast_shadows: data.cast_shadows,
+            shadow_bias: data.shadow_bias,
+            shadow_resolution: data.shadow_resolution,
         })
     }
-    pub fn new(device: &wgpu::Device, color: Color, intensity: f32) -> Self {
-        let uniform = LightUniform::new(Color::GREEN);
-        let buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Light VB"),
-                contents: bytemuck::cast_slice(&[uniform]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            }
-        );
-        let bind_group_layout = Light::bind_group_layout(device);
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-            label: None,
-        });
-        Light {intensity, color, uniform, buffer, bind_group}
-    }
-
-    pub fn update_uniform(&mut self, transform: &Transform) {
-        self.uniform.update(self.color, transform);
-    }
-}
\ No newline at end of file
+}
@@ -11,8 +11,30 @@ pub use sprite::Sprite as Sprite;
 
 pub mod light;
 pub use light::Light as Light;
+pub use light::LightType as LightType;
+pub use light::AmbientLight as AmbientLight;
+pub use light::EnvironmentLight as EnvironmentLight;
+pub use light::EnvironmentSource as EnvironmentSource;
+
+pub mod name;
+pub use name::Name as Name;
+pub use name::Tags as Tags;
+
+pub mod parent;
+pub use parent::Parent as Parent;
+
+pub mod tween;
+pub use tween::Tweener as Tweener;
+pub use tween::Tween as Tween;
 
 mod camera;
 pub use camera::Camera as Camera;
 pub use camera::CameraUniform as CameraUniform;
+pub use camera::RenderTarget as RenderTarget;
+pub use camera::UpdateMode as UpdateMode;
+pub use camera::Viewport as Viewport;
+pub use camera::ClearFlags as ClearFlags;
+pub use camera::Background as Background;
+pub use camera::Projection as Projection;
+pub use camera::OrthographicScaling as OrthographicScaling;
 
@@ -0,0 +1,48 @@
+use std::collections::BTreeSet;
+
+use serde::{Serialize, Deserialize};
+use specs::{Component, FlaggedStorage, VecStorage, DenseVecStorage};
+
+/// Unique, human readable name used to look an entity up through `EntityIndex`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Name(pub String);
+
+impl Component for Name {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Name(String::from(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Set of tags shared between any number of entities, e.g. "enemy" or "pickup".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tags(pub BTreeSet<String>);
+
+impl Component for Tags {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl Tags {
+    pub fn new(tags: &[&str]) -> Self {
+        Tags(tags.iter().map(|tag| String::from(*tag)).collect())
+    }
+
+    pub fn add(&mut self, tag: &str) {
+        self.0.insert(String::from(tag));
+    }
+
+    pub fn remove(&mut self, tag: &str) {
+        self.0.remove(tag);
+    }
+
+    pub fn has(&self, tag: &str) -> bool {
+        self.0.contains(tag)
+    }
+}
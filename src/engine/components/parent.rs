@@ -0,0 +1,6 @@
+use specs::{Component, DenseVecStorage, Entity};
+
+/// Links an entity spawned as part of a hierarchy (e.g. a prefab child) to its parent.
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+#[storage(DenseVecStorage)]
+pub struct Parent(pub Entity);
@@ -1,19 +1,23 @@
 use std::ops::Range;
 
 use glam::{Mat4, Vec3};
-use specs::{Component, VecStorage};
-use wgpu::{util::DeviceExt, Device};
+use serde::{Serialize, Deserialize};
+use specs::{Component, FlaggedStorage, VecStorage};
 
-use crate::{assets::{asset_manager::{AssetRef, AssetType, self, Asset}, AssetManager}, graphics::{GraphicsBundle, InstanceRaw, Mesh, Material, Texture, Renderable}};
+use crate::{assets::{asset_manager::{AssetRef, AssetType, self, Asset, Error}, AssetManager}, graphics::{GraphicsBundle, InstanceRaw, Mesh, Material, Texture, Renderable}, scene::{SceneComponent, SceneContext}};
 
 use super::Transform;
 
-#[derive(Component, Debug)]
-#[storage(VecStorage)]
+#[derive(Debug)]
 pub struct Sprite {
     pub mesh: AssetRef,
     pub material: AssetRef,
-    pub instance_buffer: Option<wgpu::Buffer>,
+    pub cast_shadows: bool,
+    pub receive_shadows: bool,
+}
+
+impl Component for Sprite {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
 
 impl Default for Sprite {
@@ -21,55 +25,66 @@ impl Default for Sprite {
         Sprite { 
             mesh: AssetRef { id: None, name: Some(String::from("default_sprite_plane")), asset_type: AssetType::Mesh },
             material: AssetRef { id: None, name: Some(String::from("default_material")), asset_type: AssetType::Material },
-            instance_buffer: None,
+            cast_shadows: true,
+            receive_shadows: true,
         }
     }
 }
 
 impl Sprite {
     pub fn new(material: AssetRef, mesh: AssetRef) -> Self{
-        Sprite { material: material, instance_buffer: None, mesh: mesh }
-    }
-
-    pub fn generate_instance_buffer(&mut self, bundle: &GraphicsBundle, instances: Vec<&Transform>) -> wgpu::Buffer {
-        let instance_data = instances.iter().map(|x| { Sprite::to_instance_raw(x) }).collect::<Vec<_>>();
-        let instance_buffer = bundle.device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
-                usage: wgpu::BufferUsages::VERTEX,
-            }
-        );
-        instance_buffer
+        Sprite { material: material, mesh: mesh, cast_shadows: true, receive_shadows: true }
     }
 
-    pub fn update_instance_buffer(&mut self, device: &Device, instances: Vec<&Transform>) {
-        let instance_data = instances.iter().map(|x| { Sprite::to_instance_raw(x) }).collect::<Vec<_>>();
-        let instance_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
-                usage: wgpu::BufferUsages::VERTEX,
-            }
-        );
-
-        //return instance_buffer;
-        self.instance_buffer = Some(instance_buffer);
+    pub fn with_shadows(mut self, cast: bool, receive: bool) -> Self {
+        self.cast_shadows = cast;
+        self.receive_shadows = receive;
+        self
     }
 
-    pub fn to_instance_raw(transform: &Transform) -> InstanceRaw {
+    pub fn to_instance_raw(&self, transform: &Transform) -> InstanceRaw {
         let matrix = Mat4::from_scale_rotation_translation(
-            Vec3::new(1.0, 1.0, 1.0), // scale
+            transform.scale, // scale
             transform.rotation, // rotation
             transform.position // position
         );
-        InstanceRaw {
-            model: matrix.to_cols_array_2d(),
-        }
+        InstanceRaw::new(matrix, if self.receive_shadows { InstanceRaw::RECEIVE_SHADOWS } else { 0 })
     }
 
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct SpriteData {
+    pub mesh: AssetRef,
+    pub material: AssetRef,
+    #[serde(default = "default_true")]
+    pub cast_shadows: bool,
+    #[serde(default = "default_true")]
+    pub receive_shadows: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SceneComponent for Sprite {
+    type Data = SpriteData;
+
+    fn to_scene(&self) -> SpriteData {
+        SpriteData {
+            mesh: self.mesh.clone(),
+            material: self.material.clone(),
+            cast_shadows: self.cast_shadows,
+            receive_shadows: self.receive_shadows,
+        }
+    }
+
+    fn from_scene(data: SpriteData, context: &SceneContext) -> Result<Self, Error> {
+        context.asset_manager.get_mesh(data.mesh.clone())?;
+        context.asset_manager.get_material(data.material.clone())?;
+        Ok(Sprite::new(data.material, data.mesh).with_shadows(data.cast_shadows, data.receive_shadows))
+    }
+}
 
 impl Renderable for Sprite {
     fn get_mesh<'a>(&self, asset_manager: &'a AssetManager) -> &'a Mesh { 
@@ -79,43 +94,6 @@ impl Renderable for Sprite {
         return asset_manager.get_material(self.material.clone()).unwrap();
     }
 
-    fn get_instance_buffer(&mut self, device: &wgpu::Device, instances: Vec<&Transform>) { 
-        let instance_data = instances.iter().map(|x| { Sprite::to_instance_raw(x) }).collect::<Vec<_>>();
-        let instance_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
-                usage: wgpu::BufferUsages::VERTEX,
-            }
-        );
-
-        //return instance_buffer;
-        self.instance_buffer = Some(instance_buffer);
-    }
-
-    fn draw<'a>(&'a mut self, render_pass: &mut wgpu::RenderPass<'a>, device: &wgpu::Device, asset_manager: &'a AssetManager, instances: &Transform) {
-        let mesh: &Mesh = self.get_mesh(asset_manager);
-        let material: &Material = self.get_material(asset_manager);
-        let pipeline: &wgpu::RenderPipeline = asset_manager.get_pipeline(material.render_pipeline.clone()).unwrap();
-        let diffuse: &Texture = asset_manager.get_texture(material.diffuse.clone()).unwrap();
-
-        
-        let tex_bind_group = match &diffuse.bind_group {
-            Some(v) => v,
-            None=> {panic!("material using texture without a bind group")}
-        };
-        self.get_instance_buffer(device, vec![instances]);
-        let instance_buffer= self.instance_buffer.as_ref().unwrap();
-
-        
-        render_pass.set_bind_group(1, tex_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        
-        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-        
-        render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(0..mesh.element_count, 0, 0..1 as _);
-    }
 }
 /*
 pub trait DrawSprite<'a> {
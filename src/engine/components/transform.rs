@@ -1,11 +1,12 @@
-use glam::{ Vec3, Quat, EulerRot };
+use glam::{ Vec3, Quat, EulerRot, Mat4 };
+use serde::{Serialize, Deserialize};
 use winit::event::VirtualKeyCode;
 use std::{f32::consts::PI, ops::Add};
 
 
 use crate::{
     ecs::{
-        Component, VecStorage, System, ReadStorage, Read, Write, Join, WriteStorage
+        Component, VecStorage, FlaggedStorage, System, ReadStorage, Read, Write, Join, WriteStorage
     }, input::Input
 };
 
@@ -26,14 +27,18 @@ impl Position for Vec3 {
 #[derive(Default, Debug, Clone)]
 pub struct EulerRotation{pub x: f32, pub y: f32, pub z: f32}
 
-#[derive(Component, Debug, Clone, Copy)]
-#[storage(VecStorage)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Transform {
     pub position: Vec3,
     pub rotation: Quat,
     pub scale: Vec3,
 }
 
+// flagged so `SpatialIndex` only refreshes the entities that moved
+impl Component for Transform {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
 pub trait Conversions<T> {
     fn to_rad(self) -> T;
 }
@@ -52,7 +57,7 @@ pub enum Axis {
 impl Transform {
     pub fn new(x: f32, y: f32, z: f32) -> Transform {
         
-        Transform { position: Vec3{x: x, y:y, z:z}, rotation: Quat::IDENTITY, scale: Vec3 { x:0.0, y: 0.0, z: 0.0 } }
+        Transform { position: Vec3{x: x, y:y, z:z}, rotation: Quat::IDENTITY, scale: Vec3 { x: 1.0, y: 1.0, z: 1.0 } }
     }
 
     pub fn yaw_pitch_roll(&self) -> (f32, f32, f32) {
@@ -102,5 +107,18 @@ impl Transform {
         let eul_rot = Quat::from_euler(glam::EulerRot::XYZ, eul_rad.x, eul_rad.y, eul_rad.z);
         self.rotation = eul_rot;
     }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+
+    /// Places `local`, given relative to this transform, into this transform's space.
+    pub fn mul_transform(&self, local: &Transform) -> Transform {
+        Transform {
+            position: self.position + self.rotation * (self.scale * local.position),
+            rotation: self.rotation * local.rotation,
+            scale: self.scale * local.scale,
+        }
+    }
     
 }
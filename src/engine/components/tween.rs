@@ -0,0 +1,484 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use glam::{Vec3, Quat};
+use specs::{Component, VecStorage, Entity};
+
+use crate::graphics::Color;
+
+
+/// Standard easing curves, `apply` maps a linear 0..1 progress onto the curve.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn, QuadOut, QuadInOut,
+    CubicIn, CubicOut, CubicInOut,
+    SineIn, SineOut, SineInOut,
+    ExpoIn, ExpoOut, ExpoInOut,
+    BackIn, BackOut, BackInOut,
+    ElasticOut,
+    BounceOut,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        const BACK: f32 = 1.70158;
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 },
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 },
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::ExpoIn => if t == 0.0 { 0.0 } else { 2f32.powf(10.0 * t - 10.0) },
+            Easing::ExpoOut => if t == 1.0 { 1.0 } else { 1.0 - 2f32.powf(-10.0 * t) },
+            Easing::ExpoInOut => {
+                if t == 0.0 || t == 1.0 { t }
+                else if t < 0.5 { 2f32.powf(20.0 * t - 10.0) / 2.0 }
+                else { (2.0 - 2f32.powf(-20.0 * t + 10.0)) / 2.0 }
+            },
+            Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Easing::BackInOut => {
+                let c = BACK * 1.525;
+                if t < 0.5 { ((2.0 * t).powi(2) * ((c + 1.0) * 2.0 * t - c)) / 2.0 }
+                else { ((2.0 * t - 2.0).powi(2) * ((c + 1.0) * (t * 2.0 - 2.0) + c) + 2.0) / 2.0 }
+            },
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 { t }
+                else { 2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0 }
+            },
+            Easing::BounceOut => {
+                const N: f32 = 7.5625;
+                const D: f32 = 2.75;
+                if t < 1.0 / D { N * t * t }
+                else if t < 2.0 / D { let t = t - 1.5 / D; N * t * t + 0.75 }
+                else if t < 2.5 / D { let t = t - 2.25 / D; N * t * t + 0.9375 }
+                else { let t = t - 2.625 / D; N * t * t + 0.984375 }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TweenLoop {
+    /// play once and finish
+    Once,
+    /// restart from the beginning forever
+    Loop,
+    /// play forwards then backwards forever
+    PingPong,
+}
+
+/// A value that can be interpolated by a tween.
+#[derive(Debug, Copy, Clone)]
+pub enum TweenValue {
+    Float(f32),
+    Vec3(Vec3),
+    Quat(Quat),
+    Color(Color),
+}
+
+impl TweenValue {
+    /// Returns `None` when the two values are different kinds.
+    pub fn lerp(&self, to: &TweenValue, t: f32) -> Option<TweenValue> {
+        match (self, to) {
+            (TweenValue::Float(a), TweenValue::Float(b)) => Some(TweenValue::Float(a + (b - a) * t)),
+            (TweenValue::Vec3(a), TweenValue::Vec3(b)) => Some(TweenValue::Vec3(a.lerp(*b, t))),
+            (TweenValue::Quat(a), TweenValue::Quat(b)) => Some(TweenValue::Quat(a.slerp(*b, t))),
+            (TweenValue::Color(a), TweenValue::Color(b)) => Some(TweenValue::Color(a.lerp(b, t))),
+            _ => None,
+        }
+    }
+}
+
+/// What a tween writes its value into.
+/// `Custom` values are stored on the `Tweener` and applied by a `TweenPropertySystem`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TweenProperty {
+    Position,
+    Rotation,
+    Scale,
+    LightColor,
+    LightIntensity,
+    Custom(String),
+}
+
+impl TweenProperty {
+    /// Whether the property can be set from values of this kind, custom ones take any.
+    pub fn accepts(&self, value: &TweenValue) -> bool {
+        matches!(
+            (self, value),
+            (TweenProperty::Position | TweenProperty::Scale, TweenValue::Vec3(_))
+                | (TweenProperty::Rotation, TweenValue::Quat(_))
+                | (TweenProperty::LightColor, TweenValue::Color(_))
+                | (TweenProperty::LightIntensity, TweenValue::Float(_))
+                | (TweenProperty::Custom(_), _)
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TweenStatus {
+    Waiting,
+    Running,
+    LoopCompleted,
+    Completed,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tween {
+    pub property: TweenProperty,
+    pub from: TweenValue,
+    pub to: TweenValue,
+    pub duration: f32,
+    pub delay: f32,
+    pub easing: Easing,
+    pub looping: TweenLoop,
+    /// name sent with completion events
+    pub label: Option<String>,
+
+    elapsed: f32,
+    reversed: bool,
+    finished: bool,
+}
+
+impl Tween {
+    pub fn new(property: TweenProperty, from: TweenValue, to: TweenValue, duration: f32) -> Self {
+        Tween {
+            property,
+            from,
+            to,
+            duration,
+            delay: 0.0,
+            easing: Easing::Linear,
+            looping: TweenLoop::Once,
+            label: None,
+            elapsed: 0.0,
+            reversed: false,
+            finished: false,
+        }
+    }
+
+    pub fn position(from: Vec3, to: Vec3, duration: f32) -> Self {
+        Tween::new(TweenProperty::Position, TweenValue::Vec3(from), TweenValue::Vec3(to), duration)
+    }
+    pub fn rotation(from: Quat, to: Quat, duration: f32) -> Self {
+        Tween::new(TweenProperty::Rotation, TweenValue::Quat(from), TweenValue::Quat(to), duration)
+    }
+    pub fn scale(from: Vec3, to: Vec3, duration: f32) -> Self {
+        Tween::new(TweenProperty::Scale, TweenValue::Vec3(from), TweenValue::Vec3(to), duration)
+    }
+    pub fn light_color(from: Color, to: Color, duration: f32) -> Self {
+        Tween::new(TweenProperty::LightColor, TweenValue::Color(from), TweenValue::Color(to), duration)
+    }
+    pub fn custom(name: &str, from: TweenValue, to: TweenValue, duration: f32) -> Self {
+        Tween::new(TweenProperty::Custom(String::from(name)), from, to, duration)
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+    pub fn with_loop(mut self, looping: TweenLoop) -> Self {
+        self.looping = looping;
+        self
+    }
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether `from` and `to` are the same kind and the property can be set from it.
+    pub fn is_valid(&self) -> bool {
+        self.from.lerp(&self.to, 0.0).is_some() && self.property.accepts(&self.from)
+    }
+
+    /// Current interpolated value, `None` if `from` and `to` are different kinds.
+    pub fn value(&self) -> Option<TweenValue> {
+        let progress = if self.duration > 0.0 { (self.elapsed - self.delay).max(0.0) / self.duration } else { 1.0 };
+        let progress = if self.reversed { 1.0 - progress } else { progress };
+        self.from.lerp(&self.to, self.easing.apply(progress))
+    }
+
+    /// Advances the tween by `delta` seconds.
+    pub fn step(&mut self, delta: f32) -> TweenStatus {
+        if self.finished {
+            return TweenStatus::Completed;
+        }
+        self.elapsed += delta;
+        if self.elapsed < self.delay {
+            return TweenStatus::Waiting;
+        }
+        if self.elapsed - self.delay < self.duration {
+            return TweenStatus::Running;
+        }
+
+        match self.looping {
+            TweenLoop::Once => {
+                self.elapsed = self.delay + self.duration;
+                self.finished = true;
+                TweenStatus::Completed
+            },
+            TweenLoop::Loop | TweenLoop::PingPong => {
+                // the delay only applies before the first cycle
+                let overshoot = if self.duration > 0.0 { (self.elapsed - self.delay - self.duration) % self.duration } else { 0.0 };
+                self.elapsed = self.delay + overshoot;
+                if self.looping == TweenLoop::PingPong {
+                    self.reversed = !self.reversed;
+                }
+                TweenStatus::LoopCompleted
+            },
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.reversed = false;
+        self.finished = false;
+    }
+}
+
+/// Tweens played one after another.
+#[derive(Debug, Clone)]
+pub struct TweenSequence {
+    pub tweens: Vec<Tween>,
+    pub label: Option<String>,
+    current: usize,
+}
+
+impl TweenSequence {
+    /// Tweens that aren't `is_valid` are dropped with a warning.
+    pub fn new(tweens: Vec<Tween>) -> Self {
+        let tweens = tweens.into_iter()
+            .filter(|tween| {
+                let valid = tween.is_valid();
+                if !valid {
+                    eprintln!("TweenSequence : cannot tween {:?} from {:?} to {:?}, dropping it", tween.property, tween.from, tween.to);
+                }
+                valid
+            })
+            .collect();
+        TweenSequence { tweens, label: None, current: 0 }
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    pub fn current(&mut self) -> Option<&mut Tween> {
+        self.tweens.get_mut(self.current)
+    }
+
+    pub fn advance(&mut self) {
+        self.current += 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.tweens.len()
+    }
+}
+
+/// Runs any number of tween sequences on an entity in parallel.
+#[derive(Component, Debug, Default, Clone)]
+#[storage(VecStorage)]
+pub struct Tweener {
+    pub sequences: Vec<TweenSequence>,
+    /// latest values of `TweenProperty::Custom` tweens, keyed by property name
+    pub values: HashMap<String, TweenValue>,
+    pub paused: bool,
+}
+
+impl Tweener {
+    pub fn new() -> Self {
+        Tweener { sequences: Vec::new(), values: HashMap::new(), paused: false }
+    }
+
+    pub fn with(mut self, tween: Tween) -> Self {
+        self.play(tween);
+        self
+    }
+
+    pub fn with_sequence(mut self, sequence: TweenSequence) -> Self {
+        self.sequences.push(sequence);
+        self
+    }
+
+    pub fn play(&mut self, tween: Tween) {
+        self.sequences.push(TweenSequence::new(vec![tween]));
+    }
+
+    pub fn play_sequence(&mut self, tweens: Vec<Tween>) {
+        self.sequences.push(TweenSequence::new(tweens));
+    }
+
+    pub fn get_value(&self, property: &str) -> Option<&TweenValue> {
+        self.values.get(property)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.sequences.iter().all(|sequence| sequence.is_finished())
+    }
+
+    pub fn clear(&mut self) {
+        self.sequences.clear();
+        self.values.clear();
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TweenEventKind {
+    /// a single tween reached its end
+    Completed,
+    /// a looping tween finished one cycle
+    LoopCompleted,
+    /// every tween in a sequence has completed
+    SequenceCompleted,
+}
+
+/// Sent through `EventChannel<TweenEvent>` by the `TweenSystem`.
+#[derive(Debug, Clone)]
+pub struct TweenEvent {
+    pub entity: Entity,
+    pub label: Option<String>,
+    pub kind: TweenEventKind,
+}
+
+/// Implemented by components that expose `TweenProperty::Custom` properties.
+pub trait Tweenable {
+    fn set_tween_value(&mut self, property: &str, value: &TweenValue);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 18] = [
+        Easing::Linear,
+        Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+        Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+        Easing::SineIn, Easing::SineOut, Easing::SineInOut,
+        Easing::ExpoIn, Easing::ExpoOut, Easing::ExpoInOut,
+        Easing::BackIn, Easing::BackOut, Easing::BackInOut,
+        Easing::ElasticOut,
+        Easing::BounceOut,
+    ];
+
+    fn float(value: Option<TweenValue>) -> f32 {
+        match value {
+            Some(TweenValue::Float(value)) => value,
+            other => panic!("expected a float, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing in EASINGS {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{:?} starts at {}", easing, easing.apply(0.0));
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{:?} ends at {}", easing, easing.apply(1.0));
+        }
+    }
+
+    #[test]
+    fn easings_clamp_their_progress() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(2.0), easing.apply(1.0), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn in_out_easings_are_halfway_at_the_middle() {
+        for easing in [Easing::Linear, Easing::QuadInOut, Easing::CubicInOut, Easing::SineInOut, Easing::ExpoInOut, Easing::BackInOut] {
+            assert!((easing.apply(0.5) - 0.5).abs() < 1e-5, "{:?} is at {}", easing, easing.apply(0.5));
+        }
+        assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+        assert_eq!(Easing::QuadOut.apply(0.5), 0.75);
+    }
+
+    #[test]
+    fn back_easings_overshoot() {
+        assert!(Easing::BackIn.apply(0.2) < 0.0);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+    }
+
+    #[test]
+    fn lerps_values_of_the_same_kind() {
+        assert_eq!(float(TweenValue::Float(2.0).lerp(&TweenValue::Float(4.0), 0.25)), 2.5);
+
+        let Some(TweenValue::Vec3(vec)) = TweenValue::Vec3(Vec3::ZERO).lerp(&TweenValue::Vec3(Vec3::new(2.0, 4.0, 6.0)), 0.5) else {
+            panic!("expected a vector");
+        };
+        assert_eq!(vec, Vec3::new(1.0, 2.0, 3.0));
+
+        let to = Quat::from_rotation_y(PI / 2.0);
+        let Some(TweenValue::Quat(quat)) = TweenValue::Quat(Quat::IDENTITY).lerp(&TweenValue::Quat(to), 0.5) else {
+            panic!("expected a rotation");
+        };
+        assert!(quat.abs_diff_eq(Quat::from_rotation_y(PI / 4.0), 1e-5));
+
+        let Some(TweenValue::Color(color)) = TweenValue::Color(Color::BLACK).lerp(&TweenValue::Color(Color::WHITE), 0.5) else {
+            panic!("expected a color");
+        };
+        assert_eq!(color.to_wgpu(), wgpu::Color { r: 0.5, g: 0.5, b: 0.5, a: 1.0 });
+    }
+
+    #[test]
+    fn lerp_between_different_kinds_is_none() {
+        assert!(TweenValue::Float(1.0).lerp(&TweenValue::Vec3(Vec3::ONE), 0.5).is_none());
+        assert!(TweenValue::Color(Color::RED).lerp(&TweenValue::Quat(Quat::IDENTITY), 0.5).is_none());
+        let tween = Tween::custom("mixed", TweenValue::Float(0.0), TweenValue::Vec3(Vec3::ONE), 1.0);
+        assert!(tween.value().is_none());
+    }
+
+    #[test]
+    fn sequences_drop_mismatched_tweens() {
+        let sequence = TweenSequence::new(vec![
+            Tween::new(TweenProperty::Position, TweenValue::Float(0.0), TweenValue::Float(1.0), 1.0),
+            Tween::custom("mixed", TweenValue::Float(0.0), TweenValue::Vec3(Vec3::ONE), 1.0),
+            Tween::custom("value", TweenValue::Float(0.0), TweenValue::Float(1.0), 1.0),
+            Tween::scale(Vec3::ZERO, Vec3::ONE, 1.0),
+        ]);
+        let properties: Vec<&TweenProperty> = sequence.tweens.iter().map(|tween| &tween.property).collect();
+        assert_eq!(properties, [&TweenProperty::Custom(String::from("value")), &TweenProperty::Scale]);
+        assert!(Tweener::new().with(Tween::light_color(Color::RED, Color::BLUE, 1.0)).sequences[0].tweens[0].is_valid());
+    }
+
+    #[test]
+    fn value_follows_the_easing_after_the_delay() {
+        let mut tween = Tween::custom("value", TweenValue::Float(0.0), TweenValue::Float(10.0), 2.0)
+            .with_easing(Easing::QuadIn)
+            .with_delay(1.0);
+        assert_eq!(tween.step(0.5), TweenStatus::Waiting);
+        assert_eq!(float(tween.value()), 0.0);
+        assert_eq!(tween.step(1.5), TweenStatus::Running);
+        assert_eq!(float(tween.value()), 2.5);
+        assert_eq!(tween.step(1.0), TweenStatus::Completed);
+        assert_eq!(float(tween.value()), 10.0);
+        assert!(tween.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_plays_backwards_after_each_loop() {
+        let mut tween = Tween::custom("value", TweenValue::Float(0.0), TweenValue::Float(1.0), 1.0).with_loop(TweenLoop::PingPong);
+        assert_eq!(tween.step(1.25), TweenStatus::LoopCompleted);
+        assert_eq!(float(tween.value()), 0.75);
+        assert_eq!(tween.step(1.0), TweenStatus::LoopCompleted);
+        assert_eq!(float(tween.value()), 0.25);
+        assert!(!tween.is_finished());
+    }
+}
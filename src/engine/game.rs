@@ -1,12 +1,12 @@
 use std::{
     mem,
-    borrow::BorrowMut, sync::Arc
+    borrow::BorrowMut, sync::Arc, path::Path
 };
 
 
 use chrono::{DateTime, Utc};
 use winit::{window::Window, event::{WindowEvent, VirtualKeyCode, KeyboardInput, ElementState}};
-use specs::{ World, WorldExt, Builder, shrev::Event, System, DispatcherBuilder, Dispatcher, shred::{FetchMut, Fetch} };
+use specs::{ World, WorldExt, Builder, shrev::{Event, EventChannel}, System, DispatcherBuilder, Dispatcher, shred::{FetchMut, Fetch}, Entity };
 
 use crate::{
     graphics::{Renderer, GraphicsSettings
@@ -15,6 +15,12 @@ use crate::{
     input::{
         Input,
     }, assets::AssetManager,
+    components::tween::TweenEvent,
+    systems::EntityIndex,
+    scene::{Scene, SceneRegistry, SceneContext, PrefabOverrides},
+    assets::asset_manager::{Error, AssetRef},
+    components::{Transform, AmbientLight},
+    spatial::SpatialIndex,
 };
 pub struct GameEvent {
     pub f: Box<dyn FnMut() + 'static>,
@@ -35,6 +41,7 @@ pub struct GameState {
     pub size: winit::dpi::PhysicalSize<u32>,
     pub renderer: Renderer,
     pub asset_manager: AssetManager,
+    pub scene_registry: SceneRegistry,
     
     exit_events: Vec<GameEvent>,
 }
@@ -74,6 +81,7 @@ impl GameState {
         let dispatcher = DispatcherBuilder::new();
         let mut asset_manager = AssetManager::new();
         let renderer = Renderer::new(window, &mut asset_manager, GraphicsSettings::default()).await;
+        let scene_registry = SceneRegistry::new();
         
         GameState { 
             world,
@@ -81,12 +89,17 @@ impl GameState {
             size,
             exit_events,
             asset_manager,
+            scene_registry,
         }
     }
 
     pub fn init_internal_resources(&mut self, window: &Window) {
         self.world.insert(Input::new());
         self.world.insert(Time::new());
+        self.world.insert(EventChannel::<TweenEvent>::new());
+        self.world.insert(EntityIndex::default());
+        self.world.insert(SpatialIndex::new());
+        self.world.insert(AmbientLight::default());
     } 
 
 
@@ -105,6 +118,9 @@ impl GameState {
         drop(input);
         
         dispatcher.dispatch(&self.world);
+        // applies entity deletions so despawned entities leave every storage
+        self.world.maintain();
+        self.world.write_resource::<SpatialIndex>().sync(&self.world, &self.asset_manager);
 
         self.renderer.update(&self.world);
         
@@ -129,6 +145,59 @@ impl GameState {
 
     }
 
+    /// Saves every entity with a registered component to `path`, e.g. in the player's save folder,
+    /// as RON or JSON depending on the extension. `load_scene_file` reads it back.
+    pub fn save_scene<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Scene::from_world(&self.world, &self.scene_registry)?.save(path)
+    }
+
+    /// Loads a scene file from the asset folder and adds its entities to the world.
+    pub async fn load_scene(&mut self, file_name: &str) -> Result<Vec<Entity>, Error> {
+        let scene = Scene::load(file_name).await?;
+        self.add_scene(scene).await
+    }
+
+    /// Loads a scene file from anywhere on disk, like one written by `save_scene`, and adds its entities to the world.
+    pub async fn load_scene_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<Entity>, Error> {
+        let scene = Scene::read(path)?;
+        self.add_scene(scene).await
+    }
+
+    /// An environment light whose maps aren't registered is baked again from its source image.
+    async fn add_scene(&mut self, mut scene: Scene) -> Result<Vec<Entity>, Error> {
+        let bundle = self.renderer.get_graphics_bundle();
+        if let Some(environment) = &mut scene.environment_light {
+            let baked = self.asset_manager.get_texture(environment.irradiance.clone()).is_ok()
+                && self.asset_manager.get_texture(environment.specular.clone()).is_ok();
+            if let (false, Some(source)) = (baked, environment.source.clone()) {
+                let intensity = environment.intensity;
+                *environment = self.asset_manager.load_environment(&bundle, &source.file_name, source.size).await?.with_intensity(intensity);
+            }
+        }
+        let context = SceneContext { bundle: &bundle, asset_manager: &self.asset_manager };
+        scene.instantiate(&mut self.world, &self.scene_registry, &context)
+    }
+
+    /// Spawns a prefab loaded with `AssetManager::load_prefab`, returns the root entity.
+    pub fn spawn_prefab(&mut self, prefab: AssetRef, transform: Transform) -> Result<Entity, Error> {
+        self.spawn_prefab_with(prefab, transform, None)
+    }
+
+    pub fn spawn_prefab_with(&mut self, prefab: AssetRef, transform: Transform, overrides: Option<&PrefabOverrides>) -> Result<Entity, Error> {
+        let prefab = self.asset_manager.get_prefab(prefab)?;
+        let bundle = self.renderer.get_graphics_bundle();
+        let context = SceneContext { bundle: &bundle, asset_manager: &self.asset_manager };
+        prefab.spawn(&mut self.world, &self.scene_registry, &context, transform, overrides)
+    }
+
+    pub fn find_entity(&self, name: &str) -> Option<Entity> {
+        self.world.read_resource::<EntityIndex>().find_by_name(name)
+    }
+
+    pub fn find_entities_with_tag(&self, tag: &str) -> Vec<Entity> {
+        self.world.read_resource::<EntityIndex>().find_by_tag(tag).to_vec()
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         let mut input = self.world.write_resource::<Input>();
         println!("Window resized: {:?} : {:?}", new_size.width, new_size.height);
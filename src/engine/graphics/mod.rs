@@ -4,20 +4,47 @@
 // mod mesh;
 mod shared;
 mod renderer;
+pub mod render_graph;
 pub mod util;
 
 pub use renderer::Renderer as Renderer;
 pub use renderer::Renderable as Renderable;
+pub use renderer::CullingStats as CullingStats;
+pub use render_graph::RenderGraph as RenderGraph;
+pub use render_graph::RenderNode as RenderNode;
 
 pub use shared::material::Material as Material;
+pub use shared::material::MaterialUniform as MaterialUniform;
+pub use shared::material::AlphaMode as AlphaMode;
 pub use shared::mesh::Mesh as Mesh;
+pub use shared::mesh::DrawMesh as DrawMesh;
 pub use shared::model::Model as Model;
 pub use shared::model::DrawModel as DrawModel;
 pub use shared::vertex::Vertex as Vertex;
 pub use shared::texture::Texture as Texture;
+pub use shared::texture::TextureSettings as TextureSettings;
+pub use shared::texture::SamplerSettings as SamplerSettings;
 //pub use shared::camera::Camera as Camera;
 //pub use shared::camera::CameraUniform as CameraUniform;
 pub use shared::instance::InstanceRaw as InstanceRaw;
+pub use shared::instance::InstanceBuffer as InstanceBuffer;
+pub use shared::light::LightRaw as LightRaw;
+pub use shared::light::LightBuffer as LightBuffer;
+pub use shared::light::LightingUniform as LightingUniform;
+pub use shared::shadow::ShadowMaps as ShadowMaps;
+pub use shared::shadow;
+pub use shared::tonemapping::Tonemapper as Tonemapper;
+pub use shared::tonemapping::Tonemapping as Tonemapping;
+pub use shared::tonemapping::Exposure as Exposure;
+pub use shared::bloom::Bloom as Bloom;
+pub use shared::bloom::BloomSettings as BloomSettings;
+pub use shared::post_process::PostProcessStack as PostProcessStack;
+pub use shared::render_target::OffscreenTargets as OffscreenTargets;
+pub use shared::viewport_clear::ViewportClear as ViewportClear;
+pub use shared::skybox::Skybox as Skybox;
+pub use shared::environment::EnvironmentMaps as EnvironmentMaps;
+pub use shared::post_process::PostEffect as PostEffect;
+pub use shared::post_process;
 
 pub use util::graphics_bundle::GraphicsBundle as GraphicsBundle;
 pub use util::graphics_settings::GraphicsSettings as GraphicsSettings;
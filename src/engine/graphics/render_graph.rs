@@ -0,0 +1,592 @@
+use std::{collections::{HashMap, BinaryHeap}, cmp::Reverse};
+
+use crate::{
+    ecs::World,
+    graphics::{GraphicsBundle, GraphicsSettings, Texture},
+    assets::AssetManager,
+};
+
+/// Resources the renderer provides to every pass, by name.
+pub mod resources {
+    /// depth buffer of the scene, multisampled when MSAA is on
+    pub const DEPTH: &str = "depth";
+    /// HDR target the scene is rendered into, `Texture::HDR_FORMAT`
+    pub const HDR: &str = "hdr";
+    /// tonemapped image in the surface format, the input of the post-processing stack
+    pub const LDR: &str = "ldr";
+    /// the surface texture presented at the end of the frame
+    pub const SURFACE: &str = "surface";
+    /// depth array with every shadow map of the frame
+    pub const SHADOW_MAPS: &str = "shadow_maps";
+    /// storage buffer with the lights of the frame
+    pub const LIGHTS: &str = "lights";
+    /// the textures of cameras rendering into a texture, materials sample them in the scene
+    pub const CAMERA_TARGETS: &str = "camera_targets";
+}
+
+/// Size of a transient texture, recomputed when the window is resized.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureSize {
+    /// the surface's size multiplied by the factor
+    Surface(f32),
+    Fixed(u32, u32),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TransientTexture {
+    pub size: TextureSize,
+    pub format: wgpu::TextureFormat,
+    pub sample_count: u32,
+    pub usage: wgpu::TextureUsages,
+}
+
+impl TransientTexture {
+    /// Surface sized target that can be rendered into and sampled.
+    pub fn target(format: wgpu::TextureFormat) -> Self {
+        TransientTexture {
+            size: TextureSize::Surface(1.0),
+            format,
+            sample_count: 1,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        }
+    }
+
+    pub fn with_size(mut self, size: TextureSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    fn extent(&self, config: &wgpu::SurfaceConfiguration) -> wgpu::Extent3d {
+        let (width, height) = match self.size {
+            TextureSize::Surface(scale) => ((config.width as f32 * scale) as u32, (config.height as f32 * scale) as u32),
+            TextureSize::Fixed(width, height) => (width, height),
+        };
+        wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TransientBuffer {
+    pub size: wgpu::BufferAddress,
+    pub usage: wgpu::BufferUsages,
+}
+
+/// What a pass reads, writes and creates, filled in by `RenderNode::setup`.
+#[derive(Debug, Default, Clone)]
+pub struct PassBuilder {
+    reads: Vec<String>,
+    writes: Vec<String>,
+    textures: Vec<(String, TransientTexture)>,
+    buffers: Vec<(String, TransientBuffer)>,
+}
+
+impl PassBuilder {
+    pub fn read(&mut self, name: &str) -> &mut Self {
+        self.reads.push(String::from(name));
+        self
+    }
+
+    pub fn write(&mut self, name: &str) -> &mut Self {
+        self.writes.push(String::from(name));
+        self
+    }
+
+    /// Declares a texture owned by the graph and writes it. Its contents are undefined
+    /// until written, the memory may be shared with textures not in use at the same time.
+    pub fn create_texture(&mut self, name: &str, texture: TransientTexture) -> &mut Self {
+        self.textures.push((String::from(name), texture));
+        self.write(name)
+    }
+
+    /// Same as `create_texture` for buffers.
+    pub fn create_buffer(&mut self, name: &str, buffer: TransientBuffer) -> &mut Self {
+        self.buffers.push((String::from(name), buffer));
+        self.write(name)
+    }
+
+    fn uses(&self, name: &str) -> bool {
+        self.reads.iter().chain(self.writes.iter()).any(|used| used == name)
+    }
+}
+
+/// What a pass gets to record its commands with.
+pub struct PassContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub config: &'a wgpu::SurfaceConfiguration,
+    pub settings: &'a GraphicsSettings,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub world: &'a World,
+    pub asset_manager: &'a AssetManager,
+    /// group 0 of the engine's pipelines for the camera being rendered
+    pub camera_bind_group: Option<&'a wgpu::BindGroup>,
+    textures: HashMap<&'a str, &'a wgpu::TextureView>,
+    buffers: HashMap<&'a str, &'a wgpu::Buffer>,
+}
+
+impl<'a> PassContext<'a> {
+    /// A texture the pass declared, `None` for undeclared names.
+    pub fn texture(&self, name: &str) -> Option<&'a wgpu::TextureView> {
+        self.textures.get(name).copied()
+    }
+
+    pub fn buffer(&self, name: &str) -> Option<&'a wgpu::Buffer> {
+        self.buffers.get(name).copied()
+    }
+}
+
+/// The renderer's side of a frame, shared by every pass.
+pub struct FrameResources<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub config: &'a wgpu::SurfaceConfiguration,
+    pub settings: &'a GraphicsSettings,
+    pub world: &'a World,
+    pub asset_manager: &'a AssetManager,
+    pub camera_bind_group: Option<&'a wgpu::BindGroup>,
+    /// the renderer's own textures, see `resources`
+    pub textures: HashMap<&'static str, &'a wgpu::TextureView>,
+    pub buffers: HashMap<&'static str, &'a wgpu::Buffer>,
+}
+
+/// A pass added to the render graph from outside of the renderer.
+pub trait RenderNode {
+    fn name(&self) -> &str;
+    /// Declares the resources the pass uses, called again whenever the graph is rebuilt.
+    fn setup(&self, builder: &mut PassBuilder);
+    fn execute(&mut self, context: &mut PassContext);
+}
+
+/// The stages of the renderer itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BuiltinPass {
+    Shadows,
+    /// cameras rendering into a texture instead of the surface
+    CameraTargets,
+    Scene,
+    Bloom,
+    Tonemapping,
+    PostProcess,
+}
+
+impl BuiltinPass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinPass::Shadows => "Shadows",
+            BuiltinPass::CameraTargets => "Camera Targets",
+            BuiltinPass::Scene => "Scene",
+            BuiltinPass::Bloom => "Bloom",
+            BuiltinPass::Tonemapping => "Tonemapping",
+            BuiltinPass::PostProcess => "Post Process",
+        }
+    }
+
+    fn setup(&self, builder: &mut PassBuilder) {
+        match self {
+            BuiltinPass::Shadows => {
+                builder.write(resources::SHADOW_MAPS);
+            }
+            BuiltinPass::CameraTargets => {
+                builder.read(resources::SHADOW_MAPS).read(resources::LIGHTS).write(resources::CAMERA_TARGETS);
+            }
+            BuiltinPass::Scene => {
+                builder.read(resources::SHADOW_MAPS).read(resources::LIGHTS).read(resources::CAMERA_TARGETS).write(resources::HDR).write(resources::DEPTH);
+            }
+            BuiltinPass::Bloom => {
+                builder.read(resources::HDR).write(resources::HDR);
+            }
+            BuiltinPass::Tonemapping => {
+                builder.read(resources::HDR).write(resources::LDR);
+            }
+            BuiltinPass::PostProcess => {
+                builder.read(resources::LDR).read(resources::DEPTH).write(resources::SURFACE);
+            }
+        }
+    }
+}
+
+/// A pass in the graph, either run by the renderer or by a `RenderNode`.
+pub enum GraphPass {
+    Builtin(BuiltinPass),
+    Custom(Box<dyn RenderNode>),
+}
+
+impl GraphPass {
+    pub fn name(&self) -> &str {
+        match self {
+            GraphPass::Builtin(pass) => pass.name(),
+            GraphPass::Custom(node) => node.name(),
+        }
+    }
+
+    fn setup(&self) -> PassBuilder {
+        let mut builder = PassBuilder::default();
+        match self {
+            GraphPass::Builtin(pass) => pass.setup(&mut builder),
+            GraphPass::Custom(node) => node.setup(&mut builder),
+        }
+        builder
+    }
+}
+
+/// Passes declare what they read and write, the graph runs every writer of a resource
+/// before the passes only reading it. Passes writing the same resource run in the order they were added.
+///
+/// Textures and buffers created by passes are owned by the graph, ones in use at different times
+/// share memory when their descriptions match.
+pub struct RenderGraph {
+    passes: Vec<GraphPass>,
+    declarations: Vec<PassBuilder>,
+    order: Vec<usize>,
+    textures: Vec<Texture>,
+    /// name to index into `textures`
+    texture_slots: HashMap<String, usize>,
+    buffers: Vec<wgpu::Buffer>,
+    buffer_slots: HashMap<String, usize>,
+    dirty: bool,
+}
+
+impl RenderGraph {
+    /// The renderer's passes, in the order they're drawn in.
+    pub fn new() -> Self {
+        let passes = [BuiltinPass::Shadows, BuiltinPass::CameraTargets, BuiltinPass::Scene, BuiltinPass::Bloom, BuiltinPass::Tonemapping, BuiltinPass::PostProcess]
+            .into_iter()
+            .map(GraphPass::Builtin)
+            .collect();
+        RenderGraph {
+            passes,
+            declarations: Vec::new(),
+            order: Vec::new(),
+            textures: Vec::new(),
+            texture_slots: HashMap::new(),
+            buffers: Vec::new(),
+            buffer_slots: HashMap::new(),
+            dirty: true,
+        }
+    }
+
+    pub fn add_pass(&mut self, node: Box<dyn RenderNode>) {
+        self.passes.push(GraphPass::Custom(node));
+        self.dirty = true;
+    }
+
+    /// Adds the pass ahead of the pass named `before`, which runs it first among passes writing the same resource.
+    /// Adds it last when there's no such pass.
+    pub fn add_pass_before(&mut self, before: &str, node: Box<dyn RenderNode>) {
+        let index = self.passes.iter().position(|pass| pass.name() == before).unwrap_or(self.passes.len());
+        self.passes.insert(index, GraphPass::Custom(node));
+        self.dirty = true;
+    }
+
+    /// Removes a pass added with `add_pass`, the renderer's own passes stay.
+    pub fn remove_pass(&mut self, name: &str) -> Option<Box<dyn RenderNode>> {
+        let index = self.passes.iter().position(|pass| matches!(pass, GraphPass::Custom(node) if node.name() == name))?;
+        self.dirty = true;
+        match self.passes.remove(index) {
+            GraphPass::Custom(node) => Some(node),
+            GraphPass::Builtin(_) => None,
+        }
+    }
+
+    pub fn pass_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    /// Rebuilds the graph on the next frame, needed after a pass changes what it declares.
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Transient textures are recreated for the new size on the next frame.
+    pub fn resize(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Orders the passes and allocates their resources if anything changed since the last frame.
+    pub fn prepare(&mut self, bundle: &GraphicsBundle) {
+        if !self.dirty {
+            return;
+        }
+        self.declarations = self.passes.iter().map(|pass| pass.setup()).collect();
+        self.order = self.sort();
+        self.allocate(bundle);
+        self.dirty = false;
+    }
+
+    /// Indices of the passes in the order they run.
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    pub fn pass(&self, index: usize) -> &GraphPass {
+        &self.passes[index]
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Texture> {
+        self.texture_slots.get(name).map(|slot| &self.textures[*slot])
+    }
+
+    pub fn buffer(&self, name: &str) -> Option<&wgpu::Buffer> {
+        self.buffer_slots.get(name).map(|slot| &self.buffers[*slot])
+    }
+
+    /// Runs a custom pass with the renderer's resources and the graph's own.
+    pub fn execute(&mut self, index: usize, encoder: &mut wgpu::CommandEncoder, frame: &FrameResources) {
+        let RenderGraph { passes, declarations, textures, texture_slots, buffers, buffer_slots, .. } = self;
+        let GraphPass::Custom(node) = &mut passes[index] else {
+            return;
+        };
+        let declaration = &declarations[index];
+
+        let mut pass_textures: HashMap<&str, &wgpu::TextureView> = frame.textures.iter()
+            .filter(|(name, _)| declaration.uses(name))
+            .map(|(name, view)| (*name, *view))
+            .collect();
+        pass_textures.extend(texture_slots.iter()
+            .filter(|(name, _)| declaration.uses(name))
+            .map(|(name, slot)| (name.as_str(), &textures[*slot].view)));
+        let mut pass_buffers: HashMap<&str, &wgpu::Buffer> = frame.buffers.iter()
+            .filter(|(name, _)| declaration.uses(name))
+            .map(|(name, buffer)| (*name, *buffer))
+            .collect();
+        pass_buffers.extend(buffer_slots.iter()
+            .filter(|(name, _)| declaration.uses(name))
+            .map(|(name, slot)| (name.as_str(), &buffers[*slot])));
+
+        let mut context = PassContext {
+            device: frame.device,
+            queue: frame.queue,
+            config: frame.config,
+            settings: frame.settings,
+            encoder,
+            world: frame.world,
+            asset_manager: frame.asset_manager,
+            camera_bind_group: frame.camera_bind_group,
+            textures: pass_textures,
+            buffers: pass_buffers,
+        };
+        node.execute(&mut context);
+    }
+
+    /// Topological order of the passes, ties broken by the order they were added in.
+    fn sort(&self) -> Vec<usize> {
+        let count = self.passes.len();
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); count];
+
+        let mut names: Vec<&str> = self.declarations.iter()
+            .flat_map(|declaration| declaration.reads.iter().chain(declaration.writes.iter()))
+            .map(|name| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        for name in names {
+            let writers: Vec<usize> = (0..count).filter(|index| self.declarations[*index].writes.iter().any(|write| write == name)).collect();
+            let readers = (0..count).filter(|index| !writers.contains(index) && self.declarations[*index].reads.iter().any(|read| read == name));
+            for pair in writers.windows(2) {
+                edges[pair[0]].push(pair[1]);
+            }
+            if let Some(last) = writers.last() {
+                for reader in readers {
+                    edges[*last].push(reader);
+                }
+            }
+        }
+
+        let mut incoming = vec![0; count];
+        for targets in edges.iter() {
+            for target in targets {
+                incoming[*target] += 1;
+            }
+        }
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..count).filter(|index| incoming[*index] == 0).map(Reverse).collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for target in edges[index].iter() {
+                incoming[*target] -= 1;
+                if incoming[*target] == 0 {
+                    ready.push(Reverse(*target));
+                }
+            }
+        }
+
+        if order.len() < count {
+            let stuck: Vec<&str> = (0..count).filter(|index| !order.contains(index)).map(|index| self.passes[index].name()).collect();
+            eprintln!("Renderer : render graph has a cycle between {:?}, running them in the order they were added", stuck);
+            order.extend((0..count).filter(|index| incoming[*index] > 0));
+        }
+        order
+    }
+
+    /// Creates the transient resources, sharing them between ones whose lifetimes don't overlap.
+    fn allocate(&mut self, bundle: &GraphicsBundle) {
+        // position in the execution order of every pass
+        let mut position = vec![0; self.passes.len()];
+        for (step, index) in self.order.iter().enumerate() {
+            position[*index] = step;
+        }
+        let lifetime = |name: &str| -> (usize, usize) {
+            let steps = self.declarations.iter().enumerate()
+                .filter(|(_, declaration)| declaration.uses(name))
+                .map(|(index, _)| position[index]);
+            (steps.clone().min().unwrap_or(0), steps.max().unwrap_or(0))
+        };
+
+        let mut textures: Vec<(&String, &TransientTexture, (usize, usize))> = self.declarations.iter()
+            .flat_map(|declaration| declaration.textures.iter())
+            .map(|(name, texture)| (name, texture, lifetime(name)))
+            .collect();
+        textures.sort_by_key(|(_, _, (first, _))| *first);
+        let mut texture_slots = HashMap::new();
+        // description and last use of every physical texture
+        let mut physical: Vec<(TransientTexture, usize)> = Vec::new();
+        for (name, texture, (first, last)) in textures {
+            let slot = match physical.iter().position(|(existing, free_after)| existing == texture && *free_after < first) {
+                Some(slot) => slot,
+                None => {
+                    physical.push((*texture, last));
+                    physical.len() - 1
+                }
+            };
+            physical[slot].1 = last;
+            texture_slots.insert(name.clone(), slot);
+        }
+        self.textures = physical.iter().map(|(texture, _)| RenderGraph::create_texture(bundle, texture)).collect();
+        self.texture_slots = texture_slots;
+
+        let mut buffers: Vec<(&String, &TransientBuffer, (usize, usize))> = self.declarations.iter()
+            .flat_map(|declaration| declaration.buffers.iter())
+            .map(|(name, buffer)| (name, buffer, lifetime(name)))
+            .collect();
+        buffers.sort_by_key(|(_, _, (first, _))| *first);
+        let mut buffer_slots = HashMap::new();
+        let mut physical: Vec<(TransientBuffer, usize)> = Vec::new();
+        for (name, buffer, (first, last)) in buffers {
+            let slot = match physical.iter().position(|(existing, free_after)| existing == buffer && *free_after < first) {
+                Some(slot) => slot,
+                None => {
+                    physical.push((*buffer, last));
+                    physical.len() - 1
+                }
+            };
+            physical[slot].1 = last;
+            buffer_slots.insert(name.clone(), slot);
+        }
+        self.buffers = physical.iter().map(|(buffer, _)| bundle.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Render Graph Buffer"),
+            size: buffer.size,
+            usage: buffer.usage,
+            mapped_at_creation: false,
+        })).collect();
+        self.buffer_slots = buffer_slots;
+    }
+
+    fn create_texture(bundle: &GraphicsBundle, description: &TransientTexture) -> Texture {
+        let texture = bundle.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("render_graph_texture"),
+            size: description.extent(bundle.config),
+            mip_level_count: 1,
+            sample_count: description.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: description.format,
+            usage: description.usage,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = bundle.device.create_sampler(&wgpu::SamplerDescriptor::default());
+        Texture { texture, view, sampler, bind_group: None }
+    }
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        RenderGraph::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestNode {
+        name: &'static str,
+        reads: &'static [&'static str],
+        writes: &'static [&'static str],
+    }
+
+    impl RenderNode for TestNode {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn setup(&self, builder: &mut PassBuilder) {
+            for read in self.reads {
+                builder.read(read);
+            }
+            for write in self.writes {
+                builder.write(write);
+            }
+        }
+
+        fn execute(&mut self, _context: &mut PassContext) {}
+    }
+
+    fn node(name: &'static str, reads: &'static [&'static str], writes: &'static [&'static str]) -> Box<dyn RenderNode> {
+        Box::new(TestNode { name, reads, writes })
+    }
+
+    /// Pass names in the order `prepare` would run them, without allocating anything.
+    fn sorted(graph: &mut RenderGraph) -> Vec<String> {
+        graph.declarations = graph.passes.iter().map(|pass| pass.setup()).collect();
+        graph.sort().into_iter().map(|index| String::from(graph.passes[index].name())).collect()
+    }
+
+    #[test]
+    fn builtin_passes_keep_their_order() {
+        let mut graph = RenderGraph::new();
+        assert_eq!(sorted(&mut graph), ["Shadows", "Camera Targets", "Scene", "Bloom", "Tonemapping", "Post Process"]);
+    }
+
+    #[test]
+    fn writers_run_before_readers() {
+        let mut graph = RenderGraph::new();
+        graph.add_pass(node("Outline", &[resources::HDR, resources::DEPTH], &[resources::HDR]));
+        assert_eq!(sorted(&mut graph), ["Shadows", "Camera Targets", "Scene", "Bloom", "Outline", "Tonemapping", "Post Process"]);
+    }
+
+    #[test]
+    fn passes_added_before_another_run_first_among_writers() {
+        let mut graph = RenderGraph::new();
+        graph.add_pass_before("Bloom", node("Fog", &[resources::HDR], &[resources::HDR]));
+        assert_eq!(sorted(&mut graph), ["Shadows", "Camera Targets", "Scene", "Fog", "Bloom", "Tonemapping", "Post Process"]);
+    }
+
+    #[test]
+    fn custom_resources_order_passes_added_out_of_order() {
+        let mut graph = RenderGraph::new();
+        graph.add_pass(node("Composite", &["mask"], &[resources::SURFACE]));
+        graph.add_pass(node("Mask", &[resources::DEPTH], &["mask"]));
+        let order = sorted(&mut graph);
+        let position = |name: &str| order.iter().position(|pass| pass == name).unwrap();
+        assert!(position("Scene") < position("Mask"));
+        assert!(position("Mask") < position("Composite"));
+        assert!(position("Post Process") < position("Composite"));
+    }
+
+    #[test]
+    fn cycles_still_run_every_pass_once() {
+        let mut graph = RenderGraph::new();
+        graph.add_pass(node("Ping", &["pong"], &["ping"]));
+        graph.add_pass(node("Pong", &["ping"], &["pong"]));
+        let order = sorted(&mut graph);
+        assert_eq!(order, ["Shadows", "Camera Targets", "Scene", "Bloom", "Tonemapping", "Post Process", "Ping", "Pong"]);
+    }
+
+    #[test]
+    fn only_custom_passes_can_be_removed() {
+        let mut graph = RenderGraph::new();
+        graph.add_pass(node("Outline", &[resources::HDR], &[resources::HDR]));
+        assert!(graph.remove_pass("Bloom").is_none());
+        assert_eq!(graph.remove_pass("Outline").map(|node| String::from(node.name())), Some(String::from("Outline")));
+        assert_eq!(graph.pass_names(), ["Shadows", "Camera Targets", "Scene", "Bloom", "Tonemapping", "Post Process"]);
+    }
+}
@@ -1,16 +1,19 @@
-use std::{collections::HashMap, sync::Arc, rc::Rc, borrow::Cow};
+use std::{collections::{HashMap, HashSet}, sync::Arc, rc::Rc, borrow::Cow, ops::Range};
 
-use glam::Vec3;
-use specs::{WorldExt, Join};
+use glam::{Mat4, Vec3};
+use specs::{WorldExt, Join, Entity};
 use winit::window::Window;
 
 use crate::{
-    resources, ecs::{World}, 
-    components::{ModelRenderer, model_renderer, Camera, CameraUniform, Transform, Sprite, Light},
+    resources, ecs::{World}, game::Time,
+    components::{ModelRenderer, model_renderer, Camera, CameraUniform, RenderTarget, Viewport, ClearFlags, Background, Transform, Sprite, Light, LightType, AmbientLight, EnvironmentLight},
     graphics::{
         GraphicsSettings, render_pipeline, Vertex, GraphicsBundle,
-        Model, DrawModel, Material, Mesh, util::MeshPrimitives, Texture, shared::{material, instance}
-    }, assets::{AssetManager, asset_manager::{AssetType, AssetRef}}};
+        Model, DrawModel, DrawMesh, Material, Mesh, util::MeshPrimitives, Texture, AlphaMode, Tonemapper, Exposure, Bloom, PostProcessStack, OffscreenTargets, ViewportClear, Skybox, EnvironmentMaps, Color, InstanceRaw, InstanceBuffer, LightRaw, LightBuffer, LightingUniform, shadow::{self, CascadeView, PointShadow}, shared::{material, instance}
+    }, assets::{AssetManager, asset_manager::{AssetType, AssetRef}},
+    spatial::{SpatialIndex, Aabb},
+};
+use super::render_graph::{RenderGraph, GraphPass, BuiltinPass, FrameResources, resources as graph_resources};
 
 
 use wgpu::{util::DeviceExt, RenderPipeline, PipelineLayoutDescriptor, PipelineLayout, SurfaceTexture, RenderPass, Device};
@@ -30,20 +33,312 @@ pub struct Renderer {
     //pub render_pipeline: wgpu::RenderPipeline,
 
     pub depth_texture: Texture,
+    /// color target rendered into when `settings.sample_count` is above 1, resolved into the HDR target
+    pub msaa_texture: Option<Texture>,
+    /// HDR target the scene is rendered into and the pass tonemapping it onto the surface
+    pub tonemapper: Tonemapper,
+    pub bloom: Bloom,
+    /// fullscreen effects drawn after tonemapping
+    pub post_process: PostProcessStack,
+    /// render targets of the cameras rendering into a texture, by the texture's name
+    pub offscreen_targets: HashMap<String, OffscreenTargets>,
+    pub viewport_clear: ViewportClear,
+    pub skybox: Skybox,
+    /// order of the passes drawing a frame, custom passes are added here
+    pub render_graph: RenderGraph,
+    /// sample counts usable with the HDR format, always including 1
+    pub supported_sample_counts: Vec<u32>,
+    pub culling_stats: CullingStats,
+    /// per instance data of every batch drawn this frame
+    pub instance_buffer: InstanceBuffer,
+    /// per instance data of the shadow casters, shared by every shadow map
+    pub shadow_instance_buffer: InstanceBuffer,
+    pub light_buffer: LightBuffer,
+    /// split sum lookup table of the image based lighting, shared by every environment
+    pub brdf_lut: Texture,
+    pub environment_sampler: wgpu::Sampler,
+    /// bind group of the environment drawn last frame, by the names of its irradiance and specular maps
+    environment_bind_group: Option<(EnvironmentKey, wgpu::BindGroup)>,
 }
 
-pub trait Renderable {
-    fn get_mesh<'a>(&self, asset_manager: &'a AssetManager) -> &'a Mesh { todo!() }
-    fn get_material<'a>(&self, asset_manager: &'a AssetManager) -> &'a Material { todo!() }
-    fn get_instance_buffer(&mut self, device: &wgpu::Device, instances: Vec<&Transform>) { todo!() }
+/// Names of an environment's irradiance and specular maps.
+type EnvironmentKey = (Option<String>, Option<String>);
 
-    fn draw<'a>(&'a mut self, render_pass: &mut wgpu::RenderPass<'a>, device: &wgpu::Device, asset_manager: &'a AssetManager, instances: &Transform) {
-        
+/// Visible sprites sharing a pipeline, material and mesh, drawn with one instanced call.
+struct SpriteBatch<'a> {
+    pipeline: &'a wgpu::RenderPipeline,
+    material_bind_group: &'a wgpu::BindGroup,
+    mesh: &'a Mesh,
+    instances: Vec<InstanceRaw>,
+    range: Range<u32>,
+}
+
+/// Shadow casters sharing a mesh.
+struct ShadowBatch<'a> {
+    mesh: &'a Mesh,
+    /// bind group of a `Mask` material, its pixels below the cutoff don't cast
+    cutout: Option<&'a wgpu::BindGroup>,
+    instances: Vec<InstanceRaw>,
+    range: Range<u32>,
+}
+
+/// The batches a camera draws, the opaque queue first.
+struct RenderQueues<'a> {
+    /// grouped by pipeline, depth testing sorts out their order
+    opaque: Vec<SpriteBatch<'a>>,
+    /// one sprite each, back to front by view depth so each blends over what's behind it
+    transparent: Vec<SpriteBatch<'a>>,
+}
+
+impl RenderQueues<'_> {
+    fn is_empty(&self) -> bool {
+        self.opaque.is_empty() && self.transparent.is_empty()
+    }
+}
+
+/// A camera drawn this frame and the batches it sees.
+struct CameraView<'a> {
+    camera: &'a Camera,
+    queues: RenderQueues<'a>,
+    /// bind group of the cube map when the background is a skybox
+    skybox: Option<&'a wgpu::BindGroup>,
+}
+
+/// A texture drawn into by its cameras this frame.
+struct OffscreenView<'a> {
+    name: &'a str,
+    width: u32,
+    height: u32,
+    texture: &'a Texture,
+    views: Vec<CameraView<'a>>,
+}
+
+/// What the cameras of a scene pass draw into.
+struct SceneTarget<'a> {
+    color: &'a wgpu::TextureView,
+    /// resolved into with MSAA
+    resolve: Option<&'a wgpu::TextureView>,
+    depth: &'a wgpu::TextureView,
+    width: u32,
+    height: u32,
+}
+
+/// Culls and batches the sprites of each camera, sprites seen by several cameras are in `instance_data` once per camera.
+fn camera_views<'a>(world: &World, asset_manager: &'a AssetManager, cameras: &[(&'a Camera, &Transform)], skip_texture: Option<&str>, stats: &mut CullingStats, instance_data: &mut Vec<InstanceRaw>) -> Vec<CameraView<'a>> {
+    cameras.iter()
+        .map(|(camera, transform)| {
+            let queues = collect_batches(world, asset_manager, camera, transform, skip_texture, stats, instance_data);
+            CameraView { camera, queues, skybox: skybox_bind_group(asset_manager, camera) }
+        })
+        .collect()
+}
+
+/// The bind group the cube map of a skybox background got when it was loaded, the skybox is drawn over a full clear only.
+fn skybox_bind_group<'a>(asset_manager: &'a AssetManager, camera: &Camera) -> Option<&'a wgpu::BindGroup> {
+    let Background::Skybox(texture) = &camera.background else {
+        return None;
+    };
+    if camera.clear_flags != ClearFlags::ColorAndDepth {
+        return None;
+    }
+    asset_manager.get_texture(texture.clone()).ok()?.bind_group.as_ref()
+}
+
+/// Groups the opaque sprites the camera sees sharing a pipeline, material and mesh into one instanced draw each,
+/// and sorts the blended ones, appending their instances to `instance_data`.
+/// Sprites with a material using `skip_texture` are left out, a texture can't be sampled while it's rendered into.
+fn collect_batches<'a>(world: &World, asset_manager: &'a AssetManager, camera: &Camera, camera_transform: &Transform, skip_texture: Option<&str>, stats: &mut CullingStats, instance_data: &mut Vec<InstanceRaw>) -> RenderQueues<'a> {
+    let sprites = world.read_storage::<Sprite>();
+    let transforms = world.read_storage::<Transform>();
+    let spatial_index = world.read_resource::<SpatialIndex>();
+    let visible: HashSet<_> = spatial_index.query_frustum(&camera.frustum(camera_transform)).into_iter().collect();
+
+    let mut batches: Vec<SpriteBatch> = Vec::new();
+    let mut transparent: Vec<(f32, SpriteBatch)> = Vec::new();
+    let mut batch_lookup: HashMap<(usize, usize, usize), usize> = HashMap::new();
+    for (entity, sprite, transform) in (&world.entities(), &sprites, &transforms).join() {
+        stats.total += 1;
+        if !visible.contains(&entity) {
+            continue;
+        }
+        stats.visible += 1;
+
+        let mesh: &Mesh = sprite.get_mesh(asset_manager);
+        let material: &Material = sprite.get_material(asset_manager);
+        if skip_texture.is_some_and(|name| material.uses_texture(name)) {
+            continue;
+        }
+        let pipeline: &wgpu::RenderPipeline = asset_manager.get_pipeline(material.render_pipeline.clone()).unwrap();
+        let material_bind_group = match &material.bind_group {
+            Some(v) => v,
+            None=> {panic!("material '{}' was not built", material.name)}
+        };
+
+        if material.alpha_mode == AlphaMode::Blend {
+            let depth = view_depth(camera_transform, transform.position);
+            let instances = vec![sprite.to_instance_raw(transform)];
+            transparent.push((depth, SpriteBatch { pipeline, material_bind_group, mesh, instances, range: 0..0 }));
+            continue;
+        }
+        let key = (pipeline as *const _ as usize, material as *const _ as usize, mesh as *const _ as usize);
+        let index = *batch_lookup.entry(key).or_insert_with(|| {
+            batches.push(SpriteBatch { pipeline, material_bind_group, mesh, instances: Vec::new(), range: 0..0 });
+            batches.len() - 1
+        });
+        batches[index].instances.push(sprite.to_instance_raw(transform));
+    }
+    stats.culled = stats.total - stats.visible;
+
+    // fewer pipeline switches when batches using the same pipeline are drawn together
+    batches.sort_by_key(|batch| batch.pipeline as *const _ as usize);
+    sort_back_to_front(&mut transparent);
+    let mut queues = RenderQueues { opaque: batches, transparent: transparent.into_iter().map(|(_, batch)| batch).collect() };
+    for batch in queues.opaque.iter_mut().chain(queues.transparent.iter_mut()) {
+        let start = instance_data.len() as u32;
+        instance_data.append(&mut batch.instances);
+        batch.range = start..instance_data.len() as u32;
+    }
+    queues
+}
+
+/// Distance of `position` in front of the camera, along its forward axis.
+fn view_depth(camera_transform: &Transform, position: Vec3) -> f32 {
+    (position - camera_transform.position).dot(camera_transform.forward())
+}
+
+/// Farthest first by view depth, items at the same depth keep their order.
+fn sort_back_to_front<T>(queue: &mut [(f32, T)]) {
+    queue.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+}
+
+/// Draws `batches` in order, switching pipelines only when the next batch uses another one.
+fn draw_batches<'a>(render_pass: &mut wgpu::RenderPass<'a>, batches: &'a [SpriteBatch]) {
+    let mut current_pipeline: Option<&wgpu::RenderPipeline> = None;
+    for batch in batches.iter() {
+        if !current_pipeline.is_some_and(|current| std::ptr::eq(current, batch.pipeline)) {
+            render_pass.set_pipeline(batch.pipeline);
+            current_pipeline = Some(batch.pipeline);
+        }
+        render_pass.set_bind_group(1, batch.material_bind_group, &[]);
+        render_pass.draw_mesh_batch(batch.mesh, batch.range.clone());
+    }
+}
+
+/// Draws every camera into its viewport of `target`, in order. The first camera's clear color
+/// clears the whole target when its viewport covers it, the others clear only their viewport.
+/// Without cameras the target shows `Camera::DEFAULT_BACKGROUND_COLOR`, around viewports black.
+fn scene_pass(encoder: &mut wgpu::CommandEncoder, target: &SceneTarget, views: &[CameraView], lighting: &[&wgpu::BindGroup], instances: &wgpu::Buffer, viewport_clear: &ViewportClear, skybox: &Skybox) {
+    let clears_target = views.first().is_some_and(|view| view.camera.viewport == Viewport::FULL && view.camera.clear_flags == ClearFlags::ColorAndDepth);
+    let clear_color = match views.first() {
+        Some(view) if clears_target => view.camera.background.clear_color(),
+        Some(_) => Color::BLACK,
+        None => Camera::DEFAULT_BACKGROUND_COLOR,
+    };
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[
+            Some(wgpu::RenderPassColorAttachment {
+                view: target.color,
+                resolve_target: target.resolve,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color.to_wgpu()),
+                    store: true,
+                }
+            }
+        )],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: target.depth,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    });
+
+    // the lights and the environment, from group 2 on
+    for (index, bind_group) in lighting.iter().enumerate() {
+        render_pass.set_bind_group(2 + index as u32, bind_group, &[]);
+    }
+    if views.iter().any(|view| !view.queues.is_empty()) {
+        render_pass.set_vertex_buffer(1, instances.slice(..));
+    }
+    for (index, view) in views.iter().enumerate() {
+        let camera = view.camera;
+        let (x, y, width, height) = camera.viewport.to_pixels(target.width, target.height);
+        if width == 0 || height == 0 {
+            continue;
+        }
+        render_pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+        render_pass.set_scissor_rect(x, y, width, height);
+        if index > 0 || !clears_target {
+            viewport_clear.draw(&mut render_pass, camera.clear_flags, camera.background.clear_color());
+        }
+
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        draw_batches(&mut render_pass, &view.queues.opaque);
+        // after the opaque geometry so only the pixels it left empty are shaded,
+        // before the transparent one which doesn't write the depth the skybox is tested against
+        if let Some(bind_group) = &view.skybox {
+            skybox.draw(&mut render_pass, &camera.bind_group, bind_group);
+        }
+        draw_batches(&mut render_pass, &view.queues.transparent);
+    }
+}
+
+/// Renders every shadow caster into one shadow map, or one face of a point light's cube map.
+/// `pipelines` are the depth only one and the one discarding the pixels of `Mask` materials.
+fn shadow_pass(encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView, pipelines: (&wgpu::RenderPipeline, &wgpu::RenderPipeline), view_bind_group: &wgpu::BindGroup, view_offset: u32, instances: &wgpu::Buffer, batches: &[ShadowBatch]) {
+    let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Shadow Pass"),
+        color_attachments: &[],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: target,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    });
+    if batches.is_empty() {
+        return;
+    }
+    let (pipeline, cutout_pipeline) = pipelines;
+    shadow_pass.set_pipeline(pipeline);
+    shadow_pass.set_bind_group(0, view_bind_group, &[view_offset]);
+    shadow_pass.set_vertex_buffer(1, instances.slice(..));
+    for batch in batches.iter().filter(|batch| batch.cutout.is_none()) {
+        shadow_pass.draw_mesh_batch(batch.mesh, batch.range.clone());
+    }
+    let mut cutout_batches = batches.iter().filter_map(|batch| batch.cutout.map(|material| (batch, material))).peekable();
+    if cutout_batches.peek().is_some() {
+        shadow_pass.set_pipeline(cutout_pipeline);
+        for (batch, material) in cutout_batches {
+            shadow_pass.set_bind_group(1, material, &[]);
+            shadow_pass.draw_mesh_batch(batch.mesh, batch.range.clone());
+        }
     }
 }
 
+/// Counts from the last frame's frustum culling, summed over the cameras drawing to the surface.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CullingStats {
+    /// sprites considered for drawing
+    pub total: usize,
+    /// sprites inside the camera frustum
+    pub visible: usize,
+    pub culled: usize,
+}
+
+/// Something drawn with a mesh and a material from the asset manager, batched by the renderer.
+pub trait Renderable {
+    fn get_mesh<'a>(&self, asset_manager: &'a AssetManager) -> &'a Mesh;
+    fn get_material<'a>(&self, asset_manager: &'a AssetManager) -> &'a Material;
+}
+
 impl Renderer {
-    pub async fn new(window: &Window, asset_manager: &mut AssetManager, settings: GraphicsSettings) -> Self {
+    pub async fn new(window: &Window, asset_manager: &mut AssetManager, mut settings: GraphicsSettings) -> Self {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::Backends::all());
@@ -58,7 +353,8 @@ impl Renderer {
 
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
-                features: wgpu::Features::empty(),
+                // optional, samplers fall back to clamping to the edge without it
+                features: adapter.features() & wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER,
                 limits: wgpu::Limits::default(),
                 label: None,
             },
@@ -76,48 +372,52 @@ impl Renderer {
 
         surface.configure(&device, &config);
 
+        let supported_sample_counts = Renderer::supported_sample_counts(&adapter, Texture::HDR_FORMAT);
+        let compute_supported = adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        if !compute_supported && matches!(settings.exposure, Exposure::Auto { .. }) {
+            eprintln!("Renderer : auto exposure needs compute shaders, using an exposure of 0");
+        }
+        settings.sample_count = Renderer::closest_sample_count(&supported_sample_counts, settings.sample_count);
+
         let bundle = GraphicsBundle {device: &device, surface: &surface, config: &config, settings: &settings, queue: &queue};
         
 
         let depth_texture = Texture::create_depth_texture(&bundle, "depth_texture");
-
-
-        let universal_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/universal.wgsl").into()),
-        });
+        let msaa_texture = Renderer::create_msaa_texture(&bundle);
+        let tonemapper = Tonemapper::new(&bundle, compute_supported);
+        let bloom = Bloom::new(&bundle, &tonemapper.hdr_texture);
+        let post_process = PostProcessStack::new(&bundle, &depth_texture);
+        let viewport_clear = ViewportClear::new(&bundle);
+        let skybox = Skybox::new(&bundle);
 
         let universal_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
             bind_group_layouts: &[
                 &Camera::bind_group_layout(&bundle), 
-                &Texture::bind_group_layout(&bundle),
-                &Light::bind_group_layout(&bundle.device),
+                &Material::bind_group_layout(bundle.device),
+                &LightBuffer::bind_group_layout(&bundle.device),
+                &EnvironmentMaps::bind_group_layout(&bundle.device),
             ],
             push_constant_ranges: &[],
         });
 
-        let render_pipeline = render_pipeline::create_render_pipeline(&bundle, &universal_pipeline_layout, &universal_shader, false, true);
-        
-        let debug_icon_pipeline = {
-            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Light Pipeline Layout"),
-                bind_group_layouts: &[
-                    &Camera::bind_group_layout(&bundle), 
-                    &Texture::bind_group_layout(&bundle),
-                ],
-                push_constant_ranges: &[],
-            });
-            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("Debug Icon Shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/debug_icon.wgsl").into()),
-            });
-            render_pipeline::create_render_pipeline(&bundle, &layout, &shader, true, false)
-        };
-        
-
-        asset_manager.insert_asset(render_pipeline, AssetType::RenderPipeline, "Universal");
-        asset_manager.insert_asset(debug_icon_pipeline, AssetType::RenderPipeline, "Debug Icon");
+        Renderer::create_pipelines(&bundle, &universal_pipeline_layout, asset_manager);
+
+        let instance_buffer = InstanceBuffer::new(&device, "Sprite Instance Buffer");
+        let shadow_instance_buffer = InstanceBuffer::new(&device, "Shadow Instance Buffer");
+        let light_buffer = LightBuffer::new(&device, &settings);
+
+        // stand ins for the textures a material leaves out, neutral to the material's factors
+        let default_white = Texture::from_color(&bundle, [255, 255, 255, 255], Material::DEFAULT_WHITE).unwrap();
+        let default_normal = Texture::from_color(&bundle, [128, 128, 255, 255], Material::DEFAULT_NORMAL).unwrap();
+        asset_manager.insert_asset(default_white, AssetType::Texture, Material::DEFAULT_WHITE);
+        asset_manager.insert_asset(default_normal, AssetType::Texture, Material::DEFAULT_NORMAL);
+        // a uniform white environment, scaled by the ambient light when there's no environment light
+        let white = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])));
+        let default_environment = Texture::cubemap_from_images(&bundle, &vec![white; 6], EnvironmentMaps::DEFAULT);
+        asset_manager.insert_asset(default_environment, AssetType::Texture, EnvironmentMaps::DEFAULT);
+        let brdf_lut = EnvironmentMaps::brdf_lut(&bundle);
+        let environment_sampler = EnvironmentMaps::sampler(bundle.device);
         
         
 
@@ -131,6 +431,22 @@ impl Renderer {
             universal_pipeline_layout,
     
             depth_texture,
+            msaa_texture,
+            tonemapper,
+            bloom,
+            post_process,
+            offscreen_targets: HashMap::new(),
+            viewport_clear,
+            skybox,
+            render_graph: RenderGraph::new(),
+            supported_sample_counts,
+            culling_stats: CullingStats::default(),
+            instance_buffer,
+            shadow_instance_buffer,
+            light_buffer,
+            brdf_lut,
+            environment_sampler,
+            environment_bind_group: None,
         }
     }
     /*
@@ -149,6 +465,14 @@ impl Renderer {
     pub fn build_model(&mut self, meshes: Vec<Mesh>, materials: Vec<Material>, transforms: Vec<Transform>) -> Model {
         Model::new(meshes, materials, transforms)
     }
+
+    /// Registers a `width` by `height` texture under `name` for a camera to render into with `Camera::with_target`.
+    /// Materials using it have to be built after it's registered, and again if it's created anew with another size.
+    pub fn create_render_texture(&self, asset_manager: &mut AssetManager, name: &str, width: u32, height: u32) -> RenderTarget {
+        let texture = Texture::create_render_texture(&self.device, width, height, name);
+        asset_manager.insert_asset(texture, AssetType::Texture, name);
+        RenderTarget::new(AssetRef::new(None, Some(name.to_string()), AssetType::Texture), width, height)
+    }
     
 
 
@@ -162,122 +486,382 @@ impl Renderer {
        
 
         let mut models = world.write_storage::<ModelRenderer>();
-        let mut sprites = world.write_storage::<Sprite>();
+        let sprites = world.read_storage::<Sprite>();
         let mut cameras = world.write_storage::<Camera>();
-        let mut transforms = world.write_storage::<Transform>();
-        let mut lights = world.write_storage::<Light>();
-
-        let mut camera_position: Vec3 = Vec3::new(0.0, 0.0, 0.0);
-        let mut current_pipeline: &RenderPipeline;
-        
-        
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(super::Color::new(0.2, 0.4, 0.8, 1.0).to_wgpu()),
-                            store: true,
-                        }
-                    }
-                )],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
-            
-            
-            let mut camera_iter = 0;
-            for (camera, transform) in (&mut cameras, &mut transforms).join() {
-                if camera_iter != 0 {break;}
-
-                camera.update_view_proj(transform);
-                render_pass.set_bind_group(0, &camera.bind_group, &[]);
-                
-                self.queue.write_buffer(&camera.buffer, 0, bytemuck::cast_slice(&[camera.uniform]));
-
-                camera_position = transform.position;
-                camera_iter += 1;
-            }  
-
-            for (light, transform) in (&mut lights, &mut transforms).join() {
-                light.update_uniform(transform);
-
-                render_pass.set_bind_group(2, &light.bind_group, &[]);
-                self.queue.write_buffer(&light.buffer, 0, bytemuck::cast_slice(&[light.uniform]));
+        let transforms = world.read_storage::<Transform>();
+        let lights = world.read_storage::<Light>();
+        let spatial_index = world.read_resource::<SpatialIndex>();
+
+        // active cameras without a target render to the surface, the others into their texture,
+        // every camera of a texture is drawn again once one of them is due
+        let mut surface_cameras: Vec<Entity> = Vec::new();
+        let mut offscreen_cameras: HashMap<String, Vec<Entity>> = HashMap::new();
+        let mut due_targets: HashSet<String> = HashSet::new();
+        for (entity, camera, _) in (&world.entities(), &cameras, &transforms).join() {
+            if !camera.active {
+                continue;
+            }
+            let Some(target) = &camera.target else {
+                surface_cameras.push(entity);
+                continue;
+            };
+            let Some(name) = target.texture.name.clone() else {
+                continue;
+            };
+            if target.needs_update() && asset_manager.get_texture(target.texture.clone()).is_ok() {
+                due_targets.insert(name.clone());
+            }
+            offscreen_cameras.entry(name).or_default().push(entity);
+        }
+        for (camera, transform) in (&mut cameras, &transforms).join() {
+            if !camera.active {
+                continue;
             }
-            
-            
-            /*
-            for (model, transform) in (&mut models, &transforms).join() {
-                let bundle = self.get_graphics_bundle();
-                
-                model.init_instance_buffer(&bundle, vec![&transform]);
-
-                match &model.instance_buffer {
-                    Some(res) => {render_pass.set_vertex_buffer(1, res.slice(..));},
-                    None => {}
-                };    
-                /*let model: &Model = &model.get_model();
-                
-                for mesh in &model.model.meshes {
-                    let pipeline = self.render_pipelines.get(&model.model.get_mesh_material(&mesh).pipeline).unwrap();
-                    render_pass.draw_mesh(&self.device, pipeline, &model.model, mesh);
-                    
+            if let Some(target) = &mut camera.target {
+                let drawn = target.texture.name.as_ref().is_some_and(|name| due_targets.contains(name));
+                target.advance(drawn);
+                if !drawn {
+                    continue;
                 }
-                */
             }
-            */
-
-            let mut draw_queue: Vec<(&AssetRef, Box<&mut dyn  Renderable>, &Transform)> = Vec::new();
-            for (sprite, transform) in (&mut sprites, &transforms).join() {
-                let bundle = self.get_graphics_bundle();
-                
-                //let pipeline = self.render_pipelines.get("Universal").unwrap();
-                let material: &Material = sprite.get_material(asset_manager);
-                let pipeline_ref = &material.render_pipeline;
-
-                if transform.position.distance(camera_position) < 100.0 {
-                    draw_queue.push((
-                        pipeline_ref, 
-                        Box::new(sprite),
-                        transform,
-                    ))
-                    //render_pass.draw_sprite(&self.device, asset_manager, sprite, vec![transform]);
-                }                          
-            }       
-
-            let mut current_pipeline_ref: &AssetRef = &AssetRef::new(None, None, AssetType::RenderPipeline);
-            let mut current_pipeline: &wgpu::RenderPipeline;
-            for renderable in draw_queue {
-                let (pipeline_ref, object, transform) = renderable;
-
-                if current_pipeline_ref.name.is_none() || &pipeline_ref.name.as_ref().unwrap() != &current_pipeline_ref.name.as_ref().unwrap() {
-                    current_pipeline_ref = pipeline_ref;
-                    current_pipeline = asset_manager.get_pipeline(current_pipeline_ref.clone()).unwrap();
-                    //render_pass.set_pipeline(current_pipeline);
+            camera.update_view_proj(transform);
+            self.queue.write_buffer(&camera.buffer, 0, bytemuck::cast_slice(&[camera.uniform]));
+        }
+        self.offscreen_targets.retain(|name, _| offscreen_cameras.contains_key(name));
+        offscreen_cameras.retain(|name, _| due_targets.contains(name));
+
+        // in the order they're drawn in
+        let by_priority = |entities: &[Entity]| {
+            let mut views: Vec<(&Camera, &Transform)> = entities.iter()
+                .filter_map(|entity| Some((cameras.get(*entity)?, transforms.get(*entity)?)))
+                .collect();
+            views.sort_by_key(|(camera, _)| camera.priority);
+            views
+        };
+        let surface_views = by_priority(&surface_cameras);
+        // lighting follows the first camera drawn to the surface
+        let main_view = surface_views.first().copied();
+
+        let mut stats = CullingStats::default();
+        let mut instance_data: Vec<InstanceRaw> = Vec::new();
+        let surface_views = camera_views(world, asset_manager, &surface_views, None, &mut stats, &mut instance_data);
+        self.culling_stats = stats;
+
+        let offscreen_views: Vec<OffscreenView> = offscreen_cameras.iter()
+            .filter_map(|(name, entities)| {
+                let views = by_priority(entities);
+                let target = views.first()?.0.target.as_ref()?;
+                let texture = asset_manager.get_texture(target.texture.clone()).ok()?;
+                let views = camera_views(world, asset_manager, &views, Some(name), &mut CullingStats::default(), &mut instance_data);
+                Some(OffscreenView { name, width: target.width, height: target.height, texture, views })
+            })
+            .collect();
+        for view in offscreen_views.iter() {
+            let outdated = self.offscreen_targets.get(view.name).map_or(true, |targets| targets.width != view.width || targets.height != view.height);
+            if outdated {
+                let targets = OffscreenTargets::new(&self.device, &self.tonemapper, view.width, view.height, self.settings.sample_count);
+                self.offscreen_targets.insert(view.name.to_string(), targets);
+            }
+        }
+        self.instance_buffer.write(&self.device, &self.queue, &instance_data);
+
+        // every shadow casting light gets layers in the shadow maps until they run out
+        let cascade_view = main_view.map(|(camera, transform)| CascadeView::new(camera, transform, &self.settings));
+        let caster_bounds = spatial_index.bounds();
+        let shadow_capacity = self.light_buffer.shadow_maps.capacity();
+        let mut shadow_matrices: Vec<Mat4> = Vec::new();
+        let mut point_shadows: Vec<PointShadow> = Vec::new();
+        let light_data: Vec<LightRaw> = (&lights, &transforms).join()
+            .map(|(light, transform)| {
+                if let LightType::Point { range } = light.kind {
+                    if !light.cast_shadows || point_shadows.len() >= shadow::MAX_POINT_SHADOWS {
+                        return light.to_raw(transform, -1, 0);
+                    }
+                    point_shadows.push(PointShadow { position: transform.position, range, resolution: light.shadow_resolution });
+                    return light.to_raw(transform, point_shadows.len() as i32 - 1, 1);
+                }
+                let matrices = self.shadow_matrices(light, transform, cascade_view.as_ref(), &caster_bounds);
+                if matrices.is_empty() || shadow_matrices.len() + matrices.len() > shadow_capacity {
+                    return light.to_raw(transform, -1, 0);
+                }
+                let shadow_index = shadow_matrices.len() as i32;
+                shadow_matrices.extend_from_slice(&matrices);
+                light.to_raw(transform, shadow_index, matrices.len() as u32)
+            })
+            .collect();
+        let ambient = self.update_environment(asset_manager, world);
+        let lighting = LightingUniform {
+            ambient,
+            light_count: 0,
+            cascade_splits: cascade_view.as_ref().map_or([f32::MAX; 4], |view| view.uniform_splits()),
+            view_position: cascade_view.as_ref().map_or(Vec3::ZERO, |view| view.position).into(),
+            _padding: 0,
+            view_forward: cascade_view.as_ref().map_or(Vec3::Z, |view| view.forward).into(),
+            cascade_blend: self.settings.cascade_blend,
+        };
+        self.light_buffer.write(&self.device, &self.queue, lighting, &light_data);
+        self.light_buffer.shadow_maps.write(&self.queue, &shadow_matrices);
+        self.light_buffer.write_point_shadows(&self.device, &self.queue, &point_shadows);
+
+        // casters outside the camera's view can still shadow what's inside, so these aren't culled
+        let mut shadow_batches: Vec<ShadowBatch> = Vec::new();
+        if !shadow_matrices.is_empty() || !point_shadows.is_empty() {
+            let mut shadow_lookup: HashMap<(usize, usize), usize> = HashMap::new();
+            for (sprite, transform) in (&sprites, &transforms).join() {
+                if !sprite.cast_shadows {
+                    continue;
                 }
-                render_pass.set_pipeline(asset_manager.get_pipeline(current_pipeline_ref.clone()).unwrap());
-                object.draw(&mut render_pass, &self.device, &asset_manager, transform);
-                
+                let mesh: &Mesh = sprite.get_mesh(asset_manager);
+                let material: &Material = sprite.get_material(asset_manager);
+                // other materials share a batch per mesh, their alpha doesn't change the shadow
+                let cutout = match material.alpha_mode {
+                    AlphaMode::Mask { .. } => material.bind_group.as_ref(),
+                    _ => None,
+                };
+                let key = (mesh as *const _ as usize, cutout.map_or(0, |bind_group| bind_group as *const _ as usize));
+                let index = *shadow_lookup.entry(key).or_insert_with(|| {
+                    shadow_batches.push(ShadowBatch { mesh, cutout, instances: Vec::new(), range: 0..0 });
+                    shadow_batches.len() - 1
+                });
+                shadow_batches[index].instances.push(sprite.to_instance_raw(transform));
             }
+        }
+        let mut shadow_instance_data: Vec<InstanceRaw> = Vec::new();
+        for batch in shadow_batches.iter_mut() {
+            let start = shadow_instance_data.len() as u32;
+            shadow_instance_data.append(&mut batch.instances);
+            batch.range = start..shadow_instance_data.len() as u32;
+        }
+        self.shadow_instance_buffer.write(&self.device, &self.queue, &shadow_instance_data);
+
+        let bundle = GraphicsBundle { device: &self.device, surface: &self.surface, config: &self.config, settings: &self.settings, queue: &self.queue };
+        self.render_graph.prepare(&bundle);
+
+        let time = world.try_fetch::<Time>().map(|time| *time).unwrap_or_default();
+        let camera_bind_group = main_view.map(|(camera, _)| &camera.bind_group);
+        // the tonemapped image goes straight to the surface without post-processing effects
+        let ldr_view = self.post_process.input_view().unwrap_or(&view);
+        let frame = FrameResources {
+            device: &self.device,
+            queue: &self.queue,
+            config: &self.config,
+            settings: &self.settings,
+            world,
+            asset_manager,
+            camera_bind_group,
+            textures: HashMap::from([
+                (graph_resources::DEPTH, &self.depth_texture.view),
+                (graph_resources::HDR, &self.tonemapper.hdr_texture.view),
+                (graph_resources::LDR, ldr_view),
+                (graph_resources::SURFACE, &view),
+                (graph_resources::SHADOW_MAPS, &self.light_buffer.shadow_maps.texture.view),
+            ]),
+            buffers: HashMap::from([
+                (graph_resources::LIGHTS, &self.light_buffer.storage_buffer),
+            ]),
+        };
 
-
+        let environment_bind_group = &self.environment_bind_group.as_ref().expect("updated before the passes").1;
+        let order = self.render_graph.order().to_vec();
+        for index in order {
+            let pass = match self.render_graph.pass(index) {
+                GraphPass::Builtin(pass) => *pass,
+                GraphPass::Custom(_) => {
+                    self.render_graph.execute(index, &mut encoder, &frame);
+                    continue;
+                }
+            };
+            match pass {
+                BuiltinPass::Shadows => {
+                    let shadow_maps = &self.light_buffer.shadow_maps;
+                    let shadow_instances = &self.shadow_instance_buffer.buffer;
+                    for (index, layer_view) in shadow_maps.layer_views.iter().enumerate().take(shadow_matrices.len()) {
+                        let offset = shadow_maps.view_offset(index);
+                        shadow_pass(&mut encoder, layer_view, (&shadow_maps.pipeline, &shadow_maps.cutout_pipeline), &shadow_maps.view_bind_group, offset, shadow_instances, &shadow_batches);
+                    }
+                    for (slot, map) in shadow_maps.point_maps.iter().enumerate().take(point_shadows.len()) {
+                        for (face, face_view) in map.face_views.iter().enumerate() {
+                            let offset = shadow_maps.point_view_offset(slot, face);
+                            shadow_pass(&mut encoder, face_view, (&shadow_maps.point_pipeline, &shadow_maps.point_cutout_pipeline), &shadow_maps.point_view_bind_group, offset, shadow_instances, &shadow_batches);
+                        }
+                    }
+                }
+                BuiltinPass::CameraTargets => {
+                    if !offscreen_views.is_empty() {
+                        self.tonemapper.write(&bundle, time.delta);
+                    }
+                    for view in offscreen_views.iter() {
+                        let targets = &self.offscreen_targets[view.name];
+                        let (color, resolve) = targets.color_views();
+                        let target = SceneTarget { color, resolve, depth: &targets.depth_texture.view, width: view.width, height: view.height };
+                        scene_pass(&mut encoder, &target, &view.views, &[&self.light_buffer.bind_group, environment_bind_group], &self.instance_buffer.buffer, &self.viewport_clear, &self.skybox);
+                        self.tonemapper.draw_offscreen(&mut encoder, &targets.tonemapping_bind_group, &view.texture.view);
+                    }
+                }
+                BuiltinPass::Scene => {
+                    let (color, resolve) = match &self.msaa_texture {
+                        Some(msaa) => (&msaa.view, Some(&self.tonemapper.hdr_texture.view)),
+                        None => (&self.tonemapper.hdr_texture.view, None),
+                    };
+                    let target = SceneTarget { color, resolve, depth: &self.depth_texture.view, width: self.config.width, height: self.config.height };
+                    scene_pass(&mut encoder, &target, &surface_views, &[&self.light_buffer.bind_group, environment_bind_group], &self.instance_buffer.buffer, &self.viewport_clear, &self.skybox);
+                }
+                BuiltinPass::Bloom => {
+                    if self.settings.bloom.enabled {
+                        self.bloom.write(&self.queue, &self.settings.bloom);
+                        self.bloom.draw(&mut encoder, &self.tonemapper.hdr_texture.view);
+                    }
+                }
+                BuiltinPass::Tonemapping => {
+                    self.tonemapper.write(&bundle, time.delta);
+                    self.tonemapper.measure_luminance(&mut encoder, &self.settings, self.config.width, self.config.height);
+                    self.tonemapper.draw(&mut encoder, ldr_view);
+                }
+                BuiltinPass::PostProcess => {
+                    if self.post_process.input_view().is_some() {
+                        self.post_process.write(&bundle, time.time_since_start as f32 * 0.001, time.frame as u32);
+                        self.post_process.draw(&mut encoder, self.settings.sample_count, &view);
+                    }
+                }
+            }
         }
+
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
         Ok(())
     }
 
+    /// Builds the engine's pipelines for the current settings and registers them,
+    /// replacing the ones registered under the same names.
+    fn create_pipelines(bundle: &GraphicsBundle, universal_pipeline_layout: &wgpu::PipelineLayout, asset_manager: &mut AssetManager) {
+        let universal_shader = bundle.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("PBR Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/pbr.wgsl").into()),
+        });
+        let render_pipeline = render_pipeline::create_render_pipeline(bundle, universal_pipeline_layout, &universal_shader, false, true);
+        let transparent_pipeline = render_pipeline::create_render_pipeline(bundle, universal_pipeline_layout, &universal_shader, true, true);
+
+        let debug_icon_pipeline = {
+            let layout = bundle.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Light Pipeline Layout"),
+                bind_group_layouts: &[
+                    &Camera::bind_group_layout(bundle), 
+                    &Material::bind_group_layout(bundle.device),
+                ],
+                push_constant_ranges: &[],
+            });
+            let shader = bundle.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Debug Icon Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/debug_icon.wgsl").into()),
+            });
+            render_pipeline::create_render_pipeline(bundle, &layout, &shader, true, false)
+        };
+
+        asset_manager.insert_asset(render_pipeline, AssetType::RenderPipeline, Material::UNIVERSAL);
+        asset_manager.insert_asset(transparent_pipeline, AssetType::RenderPipeline, Material::UNIVERSAL_TRANSPARENT);
+        asset_manager.insert_asset(debug_icon_pipeline, AssetType::RenderPipeline, "Debug Icon");
+    }
+
+    fn create_msaa_texture(bundle: &GraphicsBundle) -> Option<Texture> {
+        match bundle.settings.sample_count > 1 {
+            true => Some(Texture::create_multisampled_target(bundle, "msaa_texture")),
+            false => None,
+        }
+    }
+
+    /// 4x when the adapter can multisample and resolve `format` and multisample the depth buffer.
+    /// wgpu 0.14 has no per count format flags and rejects render passes with any count besides 1 and 4,
+    /// so 2x, 8x and 16x can't be offered even where the adapter supports them.
+    fn supported_sample_counts(adapter: &wgpu::Adapter, format: wgpu::TextureFormat) -> Vec<u32> {
+        let color = adapter.get_texture_format_features(format).flags;
+        let depth = adapter.get_texture_format_features(Texture::DEPTH_FORMAT).flags;
+        let multisample = wgpu::TextureFormatFeatureFlags::MULTISAMPLE;
+        if color.contains(multisample | wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE) && depth.contains(multisample) {
+            vec![1, 4]
+        } else {
+            vec![1]
+        }
+    }
+
+    /// The highest supported count not above `requested`.
+    fn closest_sample_count(supported: &[u32], requested: u32) -> u32 {
+        let count = supported.iter().copied().filter(|count| *count <= requested).max().unwrap_or(1);
+        if count != requested {
+            eprintln!("Renderer : {}x MSAA is not supported, using {}x", requested, count);
+        }
+        count
+    }
+
+    /// Switches MSAA at runtime, recreating the render targets and the engine's pipelines.
+    /// Pipelines registered outside of the renderer have to be rebuilt by their owner.
+    /// Returns the sample count actually used.
+    pub fn set_sample_count(&mut self, asset_manager: &mut AssetManager, sample_count: u32) -> u32 {
+        let sample_count = Renderer::closest_sample_count(&self.supported_sample_counts, sample_count);
+        if sample_count == self.settings.sample_count {
+            return sample_count;
+        }
+        self.settings.sample_count = sample_count;
+
+        let bundle = self.get_graphics_bundle();
+        let depth_texture = Texture::create_depth_texture(&bundle, "depth_texture");
+        let msaa_texture = Renderer::create_msaa_texture(&bundle);
+        Renderer::create_pipelines(&bundle, &self.universal_pipeline_layout, asset_manager);
+        let viewport_clear = ViewportClear::new(&bundle);
+        let skybox = Skybox::new(&bundle);
+        self.depth_texture = depth_texture;
+        self.viewport_clear = viewport_clear;
+        self.skybox = skybox;
+        self.msaa_texture = msaa_texture;
+        self.offscreen_targets.clear();
+        let bundle = GraphicsBundle { device: &self.device, surface: &self.surface, config: &self.config, settings: &self.settings, queue: &self.queue };
+        self.post_process.resize(&bundle, &self.depth_texture);
+        sample_count
+    }
+
+    /// Rebuilds the environment bind group when the maps changed and returns the light scaling them,
+    /// the `EnvironmentLight`'s maps when they're loaded, otherwise the default white environment scaled by the `AmbientLight`.
+    fn update_environment(&mut self, asset_manager: &AssetManager, world: &World) -> [f32; 3] {
+        let (irradiance, specular, ambient) = match world.try_fetch::<EnvironmentLight>() {
+            Some(environment) if asset_manager.get_texture(environment.irradiance.clone()).is_ok() && asset_manager.get_texture(environment.specular.clone()).is_ok() => {
+                (environment.irradiance.clone(), environment.specular.clone(), [environment.intensity; 3])
+            },
+            _ => {
+                let ambient = world.try_fetch::<AmbientLight>().map(|ambient| *ambient).unwrap_or_default();
+                let default = AssetRef::new(None, Some(EnvironmentMaps::DEFAULT.to_string()), AssetType::Texture);
+                (default.clone(), default, ambient.radiance())
+            },
+        };
+
+        let key = (irradiance.name.clone(), specular.name.clone());
+        let changed = match &self.environment_bind_group {
+            Some((cached, _)) => *cached != key,
+            None => true,
+        };
+        if changed {
+            let irradiance = asset_manager.get_texture(irradiance).expect("the default environment is registered when the renderer is created");
+            let specular = asset_manager.get_texture(specular).expect("the default environment is registered when the renderer is created");
+            let bind_group = EnvironmentMaps::bind_group(&self.device, &self.environment_sampler, irradiance, specular, &self.brdf_lut);
+            self.environment_bind_group = Some((key, bind_group));
+        }
+        ambient
+    }
+
+    /// Light space matrices of a shadow casting light, one per shadow map it renders.
+    fn shadow_matrices(&self, light: &Light, transform: &Transform, cascade_view: Option<&CascadeView>, caster_bounds: &Aabb) -> Vec<Mat4> {
+        if !light.cast_shadows {
+            return Vec::new();
+        }
+        match light.kind {
+            LightType::Directional => match cascade_view {
+                Some(view) => view.shadow_matrices(transform.forward(), self.settings.cascade_blend, caster_bounds, self.settings.shadow_map_size),
+                None => Vec::new(),
+            },
+            LightType::Spot { range, outer_angle, .. } => vec![shadow::spot_shadow_matrix(transform.position, transform.forward(), outer_angle, range)],
+            LightType::Point { .. } => Vec::new(),
+        }
+    }
+
     pub fn get_graphics_bundle(&self) -> GraphicsBundle {
         GraphicsBundle { device: &self.device, surface: &self.surface, config: &self.config, settings: &self.settings, queue: &self.queue }
     }
@@ -298,12 +882,62 @@ impl Renderer {
         let mut cameras = world.write_component::<Camera>();
         let mut transforms = world.write_component::<Transform>();
 
+        // cameras rendering into a texture keep its aspect ratio
         for (camera, transform) in (&mut cameras, &mut transforms).join() {
-            camera.resize(new_size.width, new_size.height)
+            if camera.target.is_none() {
+                camera.resize(new_size.width, new_size.height)
+            }
         }
 
         let bundle = self.get_graphics_bundle();
 
-        self.depth_texture = Texture::create_depth_texture(&bundle, "depth_texture")
+        let depth_texture = Texture::create_depth_texture(&bundle, "depth_texture");
+        let msaa_texture = Renderer::create_msaa_texture(&bundle);
+        self.depth_texture = depth_texture;
+        self.msaa_texture = msaa_texture;
+        let bundle = GraphicsBundle { device: &self.device, surface: &self.surface, config: &self.config, settings: &self.settings, queue: &self.queue };
+        self.tonemapper.resize(&bundle);
+        self.bloom.resize(&bundle, &self.tonemapper.hdr_texture);
+        self.post_process.resize(&bundle, &self.depth_texture);
+        self.render_graph.resize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::Quat;
+
+    use super::*;
+
+    fn back_to_front(camera_transform: &Transform, positions: &[Vec3]) -> Vec<usize> {
+        let mut queue: Vec<(f32, usize)> = positions.iter().enumerate().map(|(index, position)| (view_depth(camera_transform, *position), index)).collect();
+        sort_back_to_front(&mut queue);
+        queue.into_iter().map(|(_, index)| index).collect()
+    }
+
+    #[test]
+    fn transparent_sprites_are_drawn_farthest_first() {
+        let camera = Transform::new(0.0, 0.0, -5.0);
+        let positions = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 10.0), Vec3::new(-3.0, 0.0, 5.0)];
+        assert_eq!(view_depth(&camera, positions[1]), 15.0);
+        assert_eq!(back_to_front(&camera, &positions), [1, 2, 0]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn depth_follows_the_camera_rotation() {
+        let mut camera = Transform::new(0.0, 0.0, 0.0);
+        camera.rotation = Quat::from_rotation_y(FRAC_PI_2);
+        // looking down +X, how far sprites are along Z doesn't matter
+        let positions = [Vec3::new(2.0, 0.0, 50.0), Vec3::new(8.0, 0.0, 0.0), Vec3::new(-4.0, 0.0, 0.0)];
+        assert_eq!(back_to_front(&camera, &positions), [1, 0, 2]);
+    }
+
+    #[test]
+    fn sprites_at_the_same_depth_keep_their_order() {
+        let camera = Transform::new(0.0, 0.0, 0.0);
+        let positions = [Vec3::new(-1.0, 0.0, 3.0), Vec3::new(0.0, 0.0, 6.0), Vec3::new(1.0, 0.0, 3.0), Vec3::new(0.0, 1.0, 3.0)];
+        assert_eq!(back_to_front(&camera, &positions), [1, 0, 2, 3]);
+    }
+}
@@ -0,0 +1,27 @@
+// Copies a texture onto the whole target, used to downsample mip levels.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uvs: vec2<f32>,
+}
+
+// one triangle covering the screen, uvs go from 0 to 2 so the visible part is 0 to 1
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.uvs = uvs;
+    out.clip_position = vec4<f32>(uvs * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // explicit level, the source view may be a single mip of a larger chain
+    return textureSampleLevel(source, source_sampler, in.uvs, 0.0);
+}
@@ -0,0 +1,123 @@
+// Bloom, the bright parts of the HDR target are downsampled into a chain of ever smaller
+// textures and upsampled back into each other, every level widening the glow.
+// Filters from Jorge Jimenez' "Next Generation Post Processing in Call of Duty: Advanced Warfare".
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uvs: vec2<f32>,
+}
+
+// one triangle covering the screen, uvs go from 0 to 2 so the visible part is 0 to 1
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.uvs = uvs;
+    out.clip_position = vec4<f32>(uvs * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+struct BloomParams {
+    threshold: f32,
+    // width of the soft transition below the threshold
+    knee: f32,
+    intensity: f32,
+    // spread of the upsampling filter, in texels of the smaller level
+    radius: f32,
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> params: BloomParams;
+
+fn sample_source(uvs: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source, source_sampler, uvs, 0.0).rgb;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// weighs a group of samples down by its brightness so single bright pixels don't flicker
+fn karis_weight(color: vec3<f32>) -> f32 {
+    return 1.0 / (1.0 + luminance(color));
+}
+
+// quadratic falloff below the threshold instead of a hard cut
+fn apply_threshold(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 0.0001);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 0.0001);
+    return color * contribution;
+}
+
+// 13 bilinear taps, grouped into five overlapping boxes
+fn downsample(uvs: vec2<f32>, karis_average: bool) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let a = sample_source(uvs + texel * vec2<f32>(-2.0, 2.0));
+    let b = sample_source(uvs + texel * vec2<f32>(0.0, 2.0));
+    let c = sample_source(uvs + texel * vec2<f32>(2.0, 2.0));
+    let d = sample_source(uvs + texel * vec2<f32>(-2.0, 0.0));
+    let e = sample_source(uvs);
+    let f = sample_source(uvs + texel * vec2<f32>(2.0, 0.0));
+    let g = sample_source(uvs + texel * vec2<f32>(-2.0, -2.0));
+    let h = sample_source(uvs + texel * vec2<f32>(0.0, -2.0));
+    let i = sample_source(uvs + texel * vec2<f32>(2.0, -2.0));
+    let j = sample_source(uvs + texel * vec2<f32>(-1.0, 1.0));
+    let k = sample_source(uvs + texel * vec2<f32>(1.0, 1.0));
+    let l = sample_source(uvs + texel * vec2<f32>(-1.0, -1.0));
+    let m = sample_source(uvs + texel * vec2<f32>(1.0, -1.0));
+
+    let center = (j + k + l + m) * 0.25;
+    let top_left = (a + b + d + e) * 0.25;
+    let top_right = (b + c + e + f) * 0.25;
+    let bottom_left = (d + e + g + h) * 0.25;
+    let bottom_right = (e + f + h + i) * 0.25;
+
+    var weights = vec4<f32>(0.125);
+    var center_weight = 0.5;
+    if (karis_average) {
+        weights = weights * vec4<f32>(karis_weight(top_left), karis_weight(top_right), karis_weight(bottom_left), karis_weight(bottom_right));
+        center_weight = center_weight * karis_weight(center);
+    }
+    let sum = center * center_weight + top_left * weights.x + top_right * weights.y + bottom_left * weights.z + bottom_right * weights.w;
+    return sum / (center_weight + weights.x + weights.y + weights.z + weights.w);
+}
+
+// 3x3 tent filter
+fn upsample(uvs: vec2<f32>) -> vec3<f32> {
+    let offset = params.radius / vec2<f32>(textureDimensions(source));
+    var sum = sample_source(uvs) * 4.0;
+    sum = sum + (sample_source(uvs + offset * vec2<f32>(0.0, 1.0)) + sample_source(uvs + offset * vec2<f32>(-1.0, 0.0))
+        + sample_source(uvs + offset * vec2<f32>(1.0, 0.0)) + sample_source(uvs + offset * vec2<f32>(0.0, -1.0))) * 2.0;
+    sum = sum + sample_source(uvs + offset * vec2<f32>(-1.0, 1.0)) + sample_source(uvs + offset * vec2<f32>(1.0, 1.0))
+        + sample_source(uvs + offset * vec2<f32>(-1.0, -1.0)) + sample_source(uvs + offset * vec2<f32>(1.0, -1.0));
+    return sum / 16.0;
+}
+
+// HDR target into the first level
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(apply_threshold(downsample(in.uvs, true)), 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.uvs, false), 1.0);
+}
+
+// added onto the next larger level
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.uvs), 1.0);
+}
+
+// first level added onto the HDR target
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.uvs) * params.intensity, 1.0);
+}
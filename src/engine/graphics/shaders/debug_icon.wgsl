@@ -50,15 +50,31 @@ fn vs_main(
 
 // Fragment shader
 
+// only the base color of the material is used, unlit
+// laid out like the material in pbr.wgsl
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+}
 @group(1) @binding(0)
+var<uniform> material: Material;
+@group(1) @binding(1)
 var t_diffuse: texture_2d<f32>;
-@group(1)@binding(1)
+@group(1)@binding(2)
 var s_diffuse: sampler;
 
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {    
     let texture: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    if (texture.a < material.alpha_cutoff) {
+        discard;
+    }
 
     return vec4<f32>(texture);
 }
\ No newline at end of file
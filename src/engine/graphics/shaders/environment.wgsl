@@ -0,0 +1,173 @@
+// Bakes the image based lighting of an environment cube map, one pass per face and mip level:
+// the diffuse irradiance, the specular reflection prefiltered for a roughness,
+// and the BRDF lookup table shared by every environment.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.ndc = uvs * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(out.ndc, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var environment: texture_cube<f32>;
+@group(0) @binding(1)
+var environment_sampler: sampler;
+
+struct Params {
+    face: u32,
+    roughness: f32,
+    // texels per side of the source's first level
+    source_size: f32,
+    // level read by fs_downsample
+    source_level: f32,
+}
+// set through a dynamic offset, like the face in equirectangular.wgsl
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+let PI: f32 = 3.14159265359;
+let IRRADIANCE_STEPS: u32 = 32u;
+let SAMPLE_COUNT: u32 = 256u;
+
+// direction through a point of a face, faces are +X, -X, +Y, -Y, +Z, -Z
+fn face_direction(face: u32, ndc: vec2<f32>) -> vec3<f32> {
+    switch face {
+        case 0u: { return vec3<f32>(1.0, ndc.y, -ndc.x); }
+        case 1u: { return vec3<f32>(-1.0, ndc.y, ndc.x); }
+        case 2u: { return vec3<f32>(ndc.x, 1.0, -ndc.y); }
+        case 3u: { return vec3<f32>(ndc.x, -1.0, ndc.y); }
+        case 4u: { return vec3<f32>(ndc.x, ndc.y, 1.0); }
+        default: { return vec3<f32>(-ndc.x, ndc.y, -1.0); }
+    }
+}
+
+// rotates a direction around +Z onto `normal`
+fn tangent_to_world(direction: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if (abs(normal.y) > 0.999) {
+        up = vec3<f32>(0.0, 0.0, 1.0);
+    }
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+    return normalize(tangent * direction.x + bitangent * direction.y + normal * direction.z);
+}
+
+// low discrepancy point set, spreads the samples more evenly than random ones
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    var bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2<f32>(f32(i) / f32(count), f32(bits) * 2.3283064365386963e-10);
+}
+
+// half vector around +Z distributed like GGX, so most samples land where the lobe is strong
+fn importance_sample_ggx(xi: vec2<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+@fragment
+fn fs_irradiance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(face_direction(params.face, in.ndc));
+    // cosine weighted integral over the hemisphere, in even steps of both angles
+    var irradiance = vec3<f32>(0.0);
+    for (var i: u32 = 0u; i < IRRADIANCE_STEPS * 4u; i = i + 1u) {
+        let phi = (f32(i) + 0.5) / f32(IRRADIANCE_STEPS * 4u) * 2.0 * PI;
+        for (var j: u32 = 0u; j < IRRADIANCE_STEPS; j = j + 1u) {
+            let theta = (f32(j) + 0.5) / f32(IRRADIANCE_STEPS) * 0.5 * PI;
+            let direction = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let radiance = textureSampleLevel(environment, environment_sampler, tangent_to_world(direction, normal), 0.0).rgb;
+            irradiance = irradiance + radiance * cos(theta) * sin(theta);
+        }
+    }
+    return vec4<f32>(PI * irradiance / f32(IRRADIANCE_STEPS * IRRADIANCE_STEPS * 4u), 1.0);
+}
+
+// GGX normal distribution
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the view is assumed to look straight at the surface, which loses the stretched reflections at grazing angles
+    let normal = normalize(face_direction(params.face, in.ndc));
+    if (params.roughness <= 0.0) {
+        return vec4<f32>(textureSampleLevel(environment, environment_sampler, normal, 0.0).rgb, 1.0);
+    }
+    let texel_solid_angle = 4.0 * PI / (6.0 * params.source_size * params.source_size);
+    var color = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i: u32 = 0u; i < SAMPLE_COUNT; i = i + 1u) {
+        let half_dir = tangent_to_world(importance_sample_ggx(hammersley(i, SAMPLE_COUNT), params.roughness), normal);
+        let light_dir = normalize(2.0 * dot(normal, half_dir) * half_dir - normal);
+        let n_dot_l = dot(normal, light_dir);
+        if (n_dot_l > 0.0) {
+            // a sample stands for more of the sphere where the lobe is unlikely to be sampled,
+            // read the source level whose texels cover that much, the view being the normal makes the pdf D / 4
+            let pdf = distribution_ggx(max(dot(normal, half_dir), 0.0), params.roughness) / 4.0;
+            let sample_solid_angle = 1.0 / (f32(SAMPLE_COUNT) * pdf + 0.0001);
+            let level = max(0.5 * log2(sample_solid_angle / texel_solid_angle) + 1.0, 0.0);
+            color = color + textureSampleLevel(environment, environment_sampler, light_dir, level).rgb * n_dot_l;
+            weight = weight + n_dot_l;
+        }
+    }
+    return vec4<f32>(color / max(weight, 0.0001), 1.0);
+}
+
+// averages four texels of the level above, a texel center's direction falls between them
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = face_direction(params.face, in.ndc);
+    return vec4<f32>(textureSampleLevel(environment, environment_sampler, direction, params.source_level).rgb, 1.0);
+}
+
+// Schlick-GGX with the k used for image based lighting
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    let view = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let light = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return view * light;
+}
+
+// scale and bias applied to F0 by the specular lobe, for the cosine between normal and view in x
+// and the roughness in y, in texture coordinates
+@fragment
+fn fs_brdf(in: VertexOutput) -> @location(0) vec4<f32> {
+    let n_dot_v = max(in.ndc.x * 0.5 + 0.5, 0.0001);
+    let roughness = 0.5 - in.ndc.y * 0.5;
+    let view_dir = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i: u32 = 0u; i < SAMPLE_COUNT; i = i + 1u) {
+        let half_dir = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), roughness);
+        let light_dir = normalize(2.0 * dot(view_dir, half_dir) * half_dir - view_dir);
+        let n_dot_l = max(light_dir.z, 0.0);
+        let n_dot_h = max(half_dir.z, 0.0);
+        let v_dot_h = max(dot(view_dir, half_dir), 0.0);
+        if (n_dot_l > 0.0) {
+            let visibility = geometry_smith_ibl(n_dot_v, n_dot_l, roughness) * v_dot_h / (n_dot_h * n_dot_v);
+            let fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale = scale + (1.0 - fresnel) * visibility;
+            bias = bias + fresnel * visibility;
+        }
+    }
+    return vec4<f32>(scale / f32(SAMPLE_COUNT), bias / f32(SAMPLE_COUNT), 0.0, 1.0);
+}
@@ -0,0 +1,48 @@
+// Converts an equirectangular image into the faces of a cube map, one pass per face.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.ndc = uvs * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(out.ndc, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var equirectangular: texture_2d<f32>;
+@group(0) @binding(1)
+var equirectangular_sampler: sampler;
+
+struct Face {
+    index: u32,
+}
+// the instance index can't be offset on GL, the face comes in through a dynamic offset instead
+@group(0) @binding(2)
+var<uniform> face: Face;
+
+let PI: f32 = 3.14159265359;
+
+// direction through a point of a face, faces are +X, -X, +Y, -Y, +Z, -Z
+fn face_direction(face: u32, ndc: vec2<f32>) -> vec3<f32> {
+    switch face {
+        case 0u: { return vec3<f32>(1.0, ndc.y, -ndc.x); }
+        case 1u: { return vec3<f32>(-1.0, ndc.y, ndc.x); }
+        case 2u: { return vec3<f32>(ndc.x, 1.0, -ndc.y); }
+        case 3u: { return vec3<f32>(ndc.x, -1.0, ndc.y); }
+        case 4u: { return vec3<f32>(ndc.x, ndc.y, 1.0); }
+        default: { return vec3<f32>(-ndc.x, ndc.y, -1.0); }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(face_direction(face.index, in.ndc));
+    let uv = vec2<f32>(atan2(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+    return vec4<f32>(textureSampleLevel(equirectangular, equirectangular_sampler, uv, 0.0).rgb, 1.0);
+}
@@ -0,0 +1,77 @@
+// Auto exposure, a histogram of the HDR target's log luminance is averaged into
+// the scene luminance the eye adapts towards.
+
+struct ExposureParams {
+    min_log_luminance: f32,
+    inverse_log_luminance_range: f32,
+    log_luminance_range: f32,
+    // fraction of the way to the new luminance covered this frame
+    adaptation: f32,
+    pixel_count: u32,
+    padding_0: u32,
+    padding_1: u32,
+    padding_2: u32,
+}
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: ExposureParams;
+@group(0) @binding(2)
+var<storage, read_write> histogram: array<atomic<u32>, 256>;
+// adapted scene luminance, read by the tonemapping pass
+@group(0) @binding(3)
+var<storage, read_write> luminance: array<f32>;
+
+var<workgroup> local_bins: array<atomic<u32>, 256>;
+var<workgroup> weighted_counts: array<f32, 256>;
+
+// bin 0 holds pixels too dark to count, the rest are spread over the log luminance range
+fn luminance_bin(color: vec3<f32>) -> u32 {
+    let pixel_luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    if (pixel_luminance < 0.005) {
+        return 0u;
+    }
+    let log_luminance = clamp((log2(pixel_luminance) - params.min_log_luminance) * params.inverse_log_luminance_range, 0.0, 1.0);
+    return u32(log_luminance * 254.0 + 1.0);
+}
+
+@compute @workgroup_size(16, 16)
+fn build_histogram(@builtin(global_invocation_id) id: vec3<u32>, @builtin(local_invocation_index) local_index: u32) {
+    atomicStore(&local_bins[local_index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(hdr_texture);
+    if (id.x < u32(size.x) && id.y < u32(size.y)) {
+        let color = textureLoad(hdr_texture, vec2<i32>(id.xy), 0).rgb;
+        atomicAdd(&local_bins[luminance_bin(color)], 1u);
+    }
+    workgroupBarrier();
+
+    atomicAdd(&histogram[local_index], atomicLoad(&local_bins[local_index]));
+}
+
+@compute @workgroup_size(256)
+fn average_luminance(@builtin(local_invocation_index) local_index: u32) {
+    let count = atomicLoad(&histogram[local_index]);
+    weighted_counts[local_index] = f32(count) * f32(local_index);
+    // cleared for the next frame
+    atomicStore(&histogram[local_index], 0u);
+    workgroupBarrier();
+
+    for (var cutoff: u32 = 128u; cutoff > 0u; cutoff = cutoff >> 1u) {
+        if (local_index < cutoff) {
+            weighted_counts[local_index] = weighted_counts[local_index] + weighted_counts[local_index + cutoff];
+        }
+        workgroupBarrier();
+    }
+
+    if (local_index == 0u) {
+        // `count` is the number of pixels in bin 0 here, left out of the average
+        let counted = max(f32(params.pixel_count) - f32(count), 1.0);
+        let average_bin = max(weighted_counts[0] / counted - 1.0, 0.0);
+        let average = exp2(average_bin / 254.0 * params.log_luminance_range + params.min_log_luminance);
+        let previous = luminance[0];
+        luminance[0] = previous + (average - previous) * params.adaptation;
+    }
+}
@@ -0,0 +1,361 @@
+// Vertex shader
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uvs: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec4<f32>,
+}
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) flags: u32,
+    @location(10) normal_matrix_0: vec3<f32>,
+    @location(11) normal_matrix_1: vec3<f32>,
+    @location(12) normal_matrix_2: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uvs: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) @interpolate(flat) flags: u32,
+    @location(4) world_tangent: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    var out: VertexOutput;
+    out.uvs = model.uvs;
+    out.flags = instance.flags;
+    let normal_matrix = mat3x3<f32>(
+        instance.normal_matrix_0,
+        instance.normal_matrix_1,
+        instance.normal_matrix_2,
+    );
+    out.world_normal = normalize(normal_matrix * model.normal);
+    out.world_tangent = vec4<f32>(normalize((model_matrix * vec4<f32>(model.tangent.xyz, 0.0)).xyz), model.tangent.w);
+    var world_position: vec4<f32> = model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_proj * world_position;
+    return out;
+}
+
+// Fragment shader
+
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+}
+@group(1) @binding(0)
+var<uniform> material: Material;
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+// roughness in green, metallic in blue
+@group(1) @binding(3)
+var metallic_roughness_texture: texture_2d<f32>;
+@group(1) @binding(4)
+var metallic_roughness_sampler: sampler;
+// tangent space normals, +Y up like glTF
+@group(1) @binding(5)
+var normal_texture: texture_2d<f32>;
+@group(1) @binding(6)
+var normal_sampler: sampler;
+@group(1) @binding(7)
+var occlusion_texture: texture_2d<f32>;
+@group(1) @binding(8)
+var occlusion_sampler: sampler;
+@group(1) @binding(9)
+var emissive_texture: texture_2d<f32>;
+@group(1) @binding(10)
+var emissive_sampler: sampler;
+
+let LIGHT_POINT: u32 = 0u;
+let LIGHT_DIRECTIONAL: u32 = 1u;
+let LIGHT_SPOT: u32 = 2u;
+
+struct Light {
+    position: vec3<f32>,
+    kind: u32,
+    direction: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    inner_cos: f32,
+    outer_cos: f32,
+    shadow_index: i32,
+    shadow_bias: f32,
+    shadow_count: u32,
+}
+struct Lighting {
+    ambient: vec3<f32>,
+    light_count: u32,
+    cascade_splits: vec4<f32>,
+    view_position: vec3<f32>,
+    padding: u32,
+    view_forward: vec3<f32>,
+    cascade_blend: f32,
+}
+@group(2) @binding(0)
+var<uniform> lighting: Lighting;
+@group(2) @binding(1)
+var<storage, read> lights: array<Light>;
+@group(2) @binding(2)
+var<storage, read> shadow_matrices: array<mat4x4<f32>>;
+@group(2) @binding(3)
+var shadow_maps: texture_depth_2d_array;
+@group(2) @binding(4)
+var shadow_sampler: sampler_comparison;
+// cube maps of the point lights' shadows, holding the distance to the light divided by its range
+@group(2) @binding(5)
+var point_shadow_map_0: texture_depth_cube;
+@group(2) @binding(6)
+var point_shadow_map_1: texture_depth_cube;
+@group(2) @binding(7)
+var point_shadow_map_2: texture_depth_cube;
+@group(2) @binding(8)
+var point_shadow_map_3: texture_depth_cube;
+
+// image based lighting baked by environment.wgsl, scaled by `lighting.ambient`
+@group(3) @binding(0)
+var irradiance_map: texture_cube<f32>;
+// one roughness per mip level, up to `EnvironmentMaps::SPECULAR_MIPS`, levels past the last one clamp to it
+let SPECULAR_MAX_LEVEL: f32 = 4.0;
+@group(3) @binding(1)
+var specular_map: texture_cube<f32>;
+@group(3) @binding(2)
+var brdf_lut: texture_2d<f32>;
+@group(3) @binding(3)
+var environment_sampler: sampler;
+
+let INSTANCE_RECEIVE_SHADOWS: u32 = 1u;
+
+// 0 when fully shadowed, 3x3 PCF softens the edges
+fn sample_shadow_map(layer: i32, world_position: vec3<f32>, bias: f32) -> f32 {
+    let light_space = shadow_matrices[layer] * vec4<f32>(world_position, 1.0);
+    let ndc = light_space.xyz / light_space.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
+    // outside of the shadow map counts as lit
+    if (ndc.z < 0.0 || ndc.z > 1.0 || uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        return 1.0;
+    }
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_maps));
+    let depth = ndc.z - bias;
+    var lit = 0.0;
+    for (var y: i32 = -1; y <= 1; y = y + 1) {
+        for (var x: i32 = -1; x <= 1; x = x + 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit = lit + textureSampleCompareLevel(shadow_maps, shadow_sampler, uv + offset, layer, depth);
+        }
+    }
+    return lit / 9.0;
+}
+
+fn sample_point_shadow_map(slot: i32, direction: vec3<f32>, depth: f32) -> f32 {
+    switch (slot) {
+        case 0: { return textureSampleCompareLevel(point_shadow_map_0, shadow_sampler, direction, depth); }
+        case 1: { return textureSampleCompareLevel(point_shadow_map_1, shadow_sampler, direction, depth); }
+        case 2: { return textureSampleCompareLevel(point_shadow_map_2, shadow_sampler, direction, depth); }
+        case 3: { return textureSampleCompareLevel(point_shadow_map_3, shadow_sampler, direction, depth); }
+        default: { return 1.0; }
+    }
+}
+
+fn point_shadow_factor(light: Light, world_position: vec3<f32>) -> f32 {
+    let direction = world_position - light.position;
+    let depth = length(direction) / light.range - light.shadow_bias;
+    return sample_point_shadow_map(light.shadow_index, direction, depth);
+}
+
+fn shadow_factor(light: Light, world_position: vec3<f32>) -> f32 {
+    if (light.shadow_index < 0) {
+        return 1.0;
+    }
+    if (light.kind == LIGHT_POINT) {
+        return point_shadow_factor(light, world_position);
+    }
+    if (light.shadow_count <= 1u) {
+        return sample_shadow_map(light.shadow_index, world_position, light.shadow_bias);
+    }
+
+    // directional cascades, picked by view depth and blended near their far end
+    let depth = dot(world_position - lighting.view_position, lighting.view_forward);
+    var previous = 0.0;
+    for (var i: u32 = 0u; i < light.shadow_count; i = i + 1u) {
+        let split = lighting.cascade_splits[i];
+        if (depth < split) {
+            let layer = light.shadow_index + i32(i);
+            let shadow = sample_shadow_map(layer, world_position, light.shadow_bias);
+            let blend_range = (split - previous) * lighting.cascade_blend;
+            let blend_start = split - blend_range;
+            if (depth > blend_start && i + 1u < light.shadow_count) {
+                let next = sample_shadow_map(layer + 1, world_position, light.shadow_bias);
+                return mix(shadow, next, (depth - blend_start) / blend_range);
+            }
+            return shadow;
+        }
+        previous = split;
+    }
+    return 1.0;
+}
+
+// smooth falloff reaching zero at the light's range
+fn range_attenuation(distance: f32, range: f32) -> f32 {
+    let ratio = distance / max(range, 0.0001);
+    let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+// MikkTSpace expects the interpolated normal and tangent as they are,
+// with the bitangent rebuilt per pixel, to match what the normal map was baked against
+fn surface_normal(in: VertexOutput, normal_sample: vec3<f32>) -> vec3<f32> {
+    let bitangent = cross(in.world_normal, in.world_tangent.xyz) * in.world_tangent.w;
+    let tangent_normal = (normal_sample * 2.0 - vec3<f32>(1.0)) * vec3<f32>(material.normal_scale, material.normal_scale, 1.0);
+    return normalize(tangent_normal.x * in.world_tangent.xyz + tangent_normal.y * bitangent + tangent_normal.z * in.world_normal);
+}
+
+// direction from the surface towards the light
+fn light_direction(light: Light, world_position: vec3<f32>) -> vec3<f32> {
+    if (light.kind == LIGHT_DIRECTIONAL) {
+        return -normalize(light.direction);
+    }
+    return normalize(light.position - world_position);
+}
+
+// light arriving at the surface, without the surface's own response
+fn light_radiance(light: Light, world_position: vec3<f32>) -> vec3<f32> {
+    var attenuation: f32 = 1.0;
+    if (light.kind != LIGHT_DIRECTIONAL) {
+        let to_light = light.position - world_position;
+        let distance = length(to_light);
+        attenuation = range_attenuation(distance, light.range);
+        if (light.kind == LIGHT_SPOT) {
+            let cos_angle = dot(-to_light / max(distance, 0.0001), normalize(light.direction));
+            attenuation = attenuation * smoothstep(light.outer_cos, light.inner_cos, cos_angle);
+        }
+    }
+    return light.color * light.intensity * attenuation;
+}
+
+let PI: f32 = 3.14159265359;
+
+// GGX / Trowbridge-Reitz normal distribution
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith's method with the Schlick-GGX approximation for direct lighting
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let view = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let light = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return view * light;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// the rough surfaces' blurred reflection lets through more of the diffuse light at grazing angles
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// light reflected from the environment, split sum approximation of the specular part
+fn environment_light(normal: vec3<f32>, view_dir: vec3<f32>, albedo: vec3<f32>, metallic: f32, roughness: f32) -> vec3<f32> {
+    let n_dot_v = max(dot(normal, view_dir), 0.0);
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+
+    let irradiance = textureSampleLevel(irradiance_map, environment_sampler, normal, 0.0).rgb;
+    let diffuse = (vec3<f32>(1.0) - fresnel) * (1.0 - metallic) * albedo * irradiance;
+
+    let reflection = reflect(-view_dir, normal);
+    let prefiltered = textureSampleLevel(specular_map, environment_sampler, reflection, roughness * SPECULAR_MAX_LEVEL).rgb;
+    let brdf = textureSampleLevel(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, roughness), 0.0).rg;
+    let specular = prefiltered * (fresnel * brdf.x + brdf.y);
+    return (diffuse + specular) * lighting.ambient;
+}
+
+// Cook-Torrance specular plus Lambert diffuse for one light
+fn brdf(normal: vec3<f32>, view_dir: vec3<f32>, light_dir: vec3<f32>, albedo: vec3<f32>, metallic: f32, roughness: f32) -> vec3<f32> {
+    let half_dir = normalize(view_dir + light_dir);
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    let n_dot_v = max(dot(normal, view_dir), 0.0001);
+    let n_dot_h = max(dot(normal, half_dir), 0.0);
+
+    // dielectrics reflect about 4% head on, metals tint the reflection with their albedo
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let fresnel = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
+    let specular = distribution_ggx(n_dot_h, roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * fresnel
+        / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    let diffuse = (vec3<f32>(1.0) - fresnel) * (1.0 - metallic) * albedo / PI;
+    return (diffuse + specular) * n_dot_l;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let base_color = textureSample(base_color_texture, base_color_sampler, in.uvs) * material.base_color;
+    let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, in.uvs);
+    let occlusion_sample = textureSample(occlusion_texture, occlusion_sampler, in.uvs).r;
+    let emissive = textureSample(emissive_texture, emissive_sampler, in.uvs).rgb * material.emissive;
+    let normal_sample = textureSample(normal_texture, normal_sampler, in.uvs).xyz;
+    // after the samples, their mip level comes from neighbouring pixels that could be discarded
+    if (base_color.a < material.alpha_cutoff) {
+        discard;
+    }
+
+    let metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    // very low roughness makes the highlight vanish between pixels
+    let roughness = clamp(material.roughness * metallic_roughness.g, 0.045, 1.0);
+    let occlusion = mix(1.0, occlusion_sample, material.occlusion_strength);
+    let normal = surface_normal(in, normal_sample);
+    let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+
+    var color = vec3<f32>(0.0);
+    let receive_shadows = (in.flags & INSTANCE_RECEIVE_SHADOWS) != 0u;
+    for (var i: u32 = 0u; i < lighting.light_count; i = i + 1u) {
+        let light = lights[i];
+        var radiance = light_radiance(light, in.world_position);
+        if (receive_shadows) {
+            radiance = radiance * shadow_factor(light, in.world_position);
+        }
+        let light_dir = light_direction(light, in.world_position);
+        color = color + brdf(normal, view_dir, light_dir, base_color.rgb, metallic, roughness) * radiance;
+    }
+
+    let ambient = environment_light(normal, view_dir, base_color.rgb, metallic, roughness) * occlusion;
+    return vec4<f32>(ambient + color + emissive, base_color.a);
+}
@@ -0,0 +1,80 @@
+// Renders one face of a point light's cube shadow map, the depth is the distance to the light
+
+struct PointShadowView {
+    view_proj: mat4x4<f32>,
+    light_position: vec3<f32>,
+    range: f32,
+}
+@group(0) @binding(0)
+var<uniform> shadow_view: PointShadowView;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uvs: vec2<f32>,
+}
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) uvs: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    var out: VertexOutput;
+    out.world_position = world_position.xyz;
+    out.uvs = model.uvs;
+    out.clip_position = shadow_view.view_proj * world_position;
+    return out;
+}
+
+fn light_distance(in: VertexOutput) -> f32 {
+    return clamp(length(in.world_position - shadow_view.light_position) / shadow_view.range, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @builtin(frag_depth) f32 {
+    return light_distance(in);
+}
+
+// laid out like the material in pbr.wgsl, only `Mask` materials are drawn with the cutout entry point
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+}
+@group(1) @binding(0)
+var<uniform> material: Material;
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+
+@fragment
+fn fs_cutout(in: VertexOutput) -> @builtin(frag_depth) f32 {
+    let alpha = textureSample(base_color_texture, base_color_sampler, in.uvs).a * material.base_color.a;
+    if (alpha < material.alpha_cutoff) {
+        discard;
+    }
+    return light_distance(in);
+}
@@ -0,0 +1,21 @@
+// Splits the color channels apart towards the edges of the screen like a cheap lens.
+
+struct ChromaticAberration {
+    // offset of the red and blue channels at the screen's edge, as a fraction of the screen
+    intensity: f32,
+    padding_0: f32,
+    padding_1: f32,
+    padding_2: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> params: ChromaticAberration;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = (in.uvs - vec2<f32>(0.5)) * params.intensity;
+    let red = load_linear(in.uvs - offset).r;
+    let green = load_linear(in.uvs).g;
+    let blue = load_linear(in.uvs + offset).b;
+    return store_linear(vec3<f32>(red, green, blue));
+}
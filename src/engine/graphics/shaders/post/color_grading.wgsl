@@ -0,0 +1,37 @@
+// Maps every color through a 3D lookup table stored as a strip of `size` slices of `size` x `size` texels.
+// Red increases left to right inside a slice, green top to bottom and blue from slice to slice,
+// the table is indexed with and returns sRGB encoded colors.
+
+struct ColorGrading {
+    size: f32,
+    // blend between the original and the graded color
+    contribution: f32,
+    padding_0: f32,
+    padding_1: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> params: ColorGrading;
+@group(1) @binding(1)
+var lut_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var lut_sampler: sampler;
+
+fn lut_slice(color: vec3<f32>, slice: f32) -> vec3<f32> {
+    let size = params.size;
+    let uvs = vec2<f32>(
+        (color.r * (size - 1.0) + 0.5 + slice * size) / (size * size),
+        (color.g * (size - 1.0) + 0.5) / size,
+    );
+    return textureSampleLevel(lut_texture, lut_sampler, uvs, 0.0).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let linear = load_linear(in.uvs);
+    let color = clamp(to_display(linear), vec3<f32>(0.0), vec3<f32>(1.0));
+    let blue = color.b * (params.size - 1.0);
+    let slice = floor(blue);
+    let graded = mix(lut_slice(color, slice), lut_slice(color, min(slice + 1.0, params.size - 1.0)), blue - slice);
+    return store_linear(mix(linear, from_display(graded), params.contribution));
+}
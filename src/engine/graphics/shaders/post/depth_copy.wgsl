@@ -0,0 +1,15 @@
+// Copies the depth buffer into a color texture post-processing effects can read.
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uvs * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+@group(0) @binding(0)
+var depth: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) f32 {
+    return textureLoad(depth, vec2<i32>(position.xy), 0).r;
+}
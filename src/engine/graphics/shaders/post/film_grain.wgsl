@@ -0,0 +1,31 @@
+// Noise changing every frame, strongest in the midtones.
+
+struct FilmGrain {
+    intensity: f32,
+    // size of a grain in pixels
+    size: f32,
+    padding_0: f32,
+    padding_1: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> params: FilmGrain;
+
+fn hash(position: vec3<u32>) -> f32 {
+    var h = position.x * 1664525u + position.y * 1013904223u + position.z * 2654435769u;
+    h = (h ^ (h >> 16u)) * 2246822519u;
+    h = (h ^ (h >> 13u)) * 3266489917u;
+    h = h ^ (h >> 16u);
+    return f32(h) / 4294967295.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let cell = vec2<u32>(in.clip_position.xy / max(params.size, 1.0));
+    let noise = hash(vec3<u32>(cell, post.frame)) - 0.5;
+    let color = to_display(load_linear(in.uvs));
+    let luma = dot(color, vec3<f32>(0.299, 0.587, 0.114));
+    let midtones = 1.0 - abs(luma * 2.0 - 1.0);
+    let grainy = clamp(color + noise * params.intensity * midtones, vec3<f32>(0.0), vec3<f32>(1.0));
+    return store_linear(from_display(grainy));
+}
@@ -0,0 +1,39 @@
+// FXAA, blurs along the edges found in the image's luma.
+
+let FXAA_SPAN_MAX: f32 = 8.0;
+let FXAA_REDUCE_MUL: f32 = 0.125;
+let FXAA_REDUCE_MIN: f32 = 0.0078125;
+
+fn luma(uvs: vec2<f32>) -> f32 {
+    return dot(to_display(load_linear(uvs)), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = post.texel_size;
+    let luma_nw = luma(in.uvs + vec2<f32>(-1.0, -1.0) * texel);
+    let luma_ne = luma(in.uvs + vec2<f32>(1.0, -1.0) * texel);
+    let luma_sw = luma(in.uvs + vec2<f32>(-1.0, 1.0) * texel);
+    let luma_se = luma(in.uvs + vec2<f32>(1.0, 1.0) * texel);
+    let luma_m = luma(in.uvs);
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // perpendicular to the edge
+    var direction = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    let inverse_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * inverse_min, vec2<f32>(-FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX)) * texel;
+
+    let near = 0.5 * (load_linear(in.uvs + direction * (1.0 / 3.0 - 0.5)) + load_linear(in.uvs + direction * (2.0 / 3.0 - 0.5)));
+    let far = near * 0.5 + 0.25 * (load_linear(in.uvs - direction * 0.5) + load_linear(in.uvs + direction * 0.5));
+    let luma_far = dot(to_display(far), vec3<f32>(0.299, 0.587, 0.114));
+    // the wider blur went past the edge
+    if (luma_far < luma_min || luma_far > luma_max) {
+        return store_linear(near);
+    }
+    return store_linear(far);
+}
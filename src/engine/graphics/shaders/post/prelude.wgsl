@@ -0,0 +1,79 @@
+// Shared by every post-processing effect, the effect's source is appended to this one.
+// An effect implements `fs_main(in: VertexOutput) -> @location(0) vec4<f32>`, returning what
+// `store_linear` gives back. Its own resources are in group 1:
+//   binding 0 - uniform with the effect's parameters
+//   binding 1 - texture_2d<f32>, e.g. a lookup table
+//   binding 2 - sampler for that texture
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uvs: vec2<f32>,
+}
+
+// one triangle covering the screen, uvs go from 0 to 2 so the visible part is 0 to 1
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.uvs = uvs;
+    out.clip_position = vec4<f32>(uvs * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+struct PostProcess {
+    resolution: vec2<f32>,
+    texel_size: vec2<f32>,
+    // seconds since the start
+    time: f32,
+    frame: u32,
+    // set when the targets convert to sRGB on their own
+    srgb_target: u32,
+    padding: u32,
+}
+
+// output of the previous effect, or the tonemapped image for the first one
+@group(0) @binding(0)
+var color_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var color_sampler: sampler;
+// the scene's depth buffer, not filterable
+@group(0) @binding(2)
+var depth_texture: texture_2d<f32>;
+@group(0) @binding(3)
+var<uniform> post: PostProcess;
+
+fn to_display(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+fn from_display(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+// linear color of the previous effect's output
+fn load_linear(uvs: vec2<f32>) -> vec3<f32> {
+    let color = textureSampleLevel(color_texture, color_sampler, uvs, 0.0).rgb;
+    if (post.srgb_target != 0u) {
+        return color;
+    }
+    return from_display(color);
+}
+
+// what to return from `fs_main` for a linear color
+fn store_linear(color: vec3<f32>) -> vec4<f32> {
+    if (post.srgb_target != 0u) {
+        return vec4<f32>(color, 1.0);
+    }
+    return vec4<f32>(to_display(color), 1.0);
+}
+
+// depth buffer value at `uvs`, 0 at the near plane and 1 at the far plane
+fn load_depth(uvs: vec2<f32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(depth_texture));
+    let position = clamp(vec2<i32>(uvs * vec2<f32>(size)), vec2<i32>(0), size - vec2<i32>(1));
+    return textureLoad(depth_texture, position, 0).r;
+}
@@ -0,0 +1,20 @@
+// Darkens the image towards its corners.
+
+struct Vignette {
+    color: vec4<f32>,
+    intensity: f32,
+    // distance from the center where the darkening starts, 1 is a corner
+    radius: f32,
+    smoothness: f32,
+    padding: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> params: Vignette;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.uvs - vec2<f32>(0.5)) * 1.41421356;
+    let amount = smoothstep(params.radius, params.radius + params.smoothness, distance) * params.intensity;
+    return store_linear(mix(load_linear(in.uvs), params.color.rgb, amount));
+}
@@ -0,0 +1,65 @@
+// Depth only pass rendering shadow casters from a light's point of view
+
+struct ShadowView {
+    view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> shadow_view: ShadowView;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uvs: vec2<f32>,
+}
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uvs: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    var out: VertexOutput;
+    out.uvs = model.uvs;
+    out.clip_position = shadow_view.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// laid out like the material in pbr.wgsl, only `Mask` materials are drawn with the cutout entry point
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+}
+@group(1) @binding(0)
+var<uniform> material: Material;
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+
+@fragment
+fn fs_cutout(in: VertexOutput) {
+    let alpha = textureSample(base_color_texture, base_color_sampler, in.uvs).a * material.base_color.a;
+    if (alpha < material.alpha_cutoff) {
+        discard;
+    }
+}
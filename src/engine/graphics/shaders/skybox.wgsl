@@ -0,0 +1,36 @@
+// Skybox, drawn at the far plane behind everything the camera's batches left the depth buffer cleared at.
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var skybox: texture_cube<f32>;
+@group(1) @binding(1)
+var skybox_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+// one triangle covering the screen at a depth of 1
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.ndc = uvs * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(out.ndc, 1.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let far = camera.inverse_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = far.xyz / far.w - camera.view_pos.xyz;
+    return vec4<f32>(textureSampleLevel(skybox, skybox_sampler, direction, 0.0).rgb, 1.0);
+}
@@ -0,0 +1,118 @@
+// Maps the HDR target onto the surface, exposure first, then a tonemapping curve.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uvs = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uvs * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+struct Tonemapping {
+    // multiplier used without auto exposure
+    exposure: f32,
+    // 0 none, 1 Reinhard, 2 ACES, 3 AgX
+    curve: u32,
+    auto_exposure: u32,
+    // set when the surface format doesn't convert to sRGB on its own
+    encode_srgb: u32,
+    // luminance auto exposure maps the scene average to
+    key_value: f32,
+    padding_0: f32,
+    padding_1: f32,
+    padding_2: f32,
+}
+
+// same size as the surface, read texel for texel
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> tonemapping: Tonemapping;
+// adapted scene luminance written by `luminance_histogram.wgsl`
+@group(0) @binding(2)
+var<storage, read> luminance: array<f32>;
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (vec3<f32>(1.0) + color);
+}
+
+// Stephen Hill's fit of the ACES reference rendering and output transforms
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let input = mat3x3<f32>(
+        0.59719, 0.07600, 0.02840,
+        0.35458, 0.90834, 0.13383,
+        0.04823, 0.01566, 0.83777,
+    );
+    let output = mat3x3<f32>(
+        1.60475, -0.10208, -0.00327,
+        -0.53108, 1.10813, -0.07276,
+        -0.07367, -0.00605, 1.07602,
+    );
+    let v = input * color;
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+    return clamp(output * (a / b), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// polynomial fit of the AgX default contrast curve
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+// Troy Sobotka's AgX, in its minimal form without a look
+fn agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3<f32>(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    var v = inset * max(color, vec3<f32>(1e-10));
+    v = clamp(log2(v), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    v = (v - min_ev) / (max_ev - min_ev);
+    v = agx_contrast(v);
+    // back from the display encoding to linear, the surface encodes it again
+    return pow(max(outset * v, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureLoad(hdr_texture, vec2<i32>(in.clip_position.xy), 0);
+
+    var exposure = tonemapping.exposure;
+    if (tonemapping.auto_exposure != 0u) {
+        exposure = tonemapping.key_value / max(luminance[0], 0.0001);
+    }
+    var color = hdr.rgb * exposure;
+
+    switch (tonemapping.curve) {
+        case 1u: { color = reinhard(color); }
+        case 2u: { color = aces(color); }
+        case 3u: { color = agx(color); }
+        default: { color = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)); }
+    }
+
+    if (tonemapping.encode_srgb != 0u) {
+        color = linear_to_srgb(color);
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,266 @@
+use crate::graphics::{GraphicsBundle, Texture};
+
+/// Levels in the downsampling chain, each half the size of the one before.
+pub const MAX_LEVELS: usize = 6;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// brightness in the HDR target above which pixels start to glow
+    pub threshold: f32,
+    /// fraction of the threshold below it that fades in softly
+    pub soft_threshold: f32,
+    /// how much of the glow is added onto the image
+    pub intensity: f32,
+    /// spread of the glow, 1 is the filter's natural size
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            enabled: true,
+            threshold: 1.0,
+            soft_threshold: 0.5,
+            intensity: 0.1,
+            radius: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    radius: f32,
+}
+
+/// Chain of half resolution HDR textures the bright parts of the scene are blurred in,
+/// added back onto the HDR target before tonemapping.
+/// Every level is its own texture, GL can't render into one mip while sampling another.
+#[derive(Debug)]
+pub struct Bloom {
+    pub levels: Vec<Texture>,
+    pub uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// samples the HDR target
+    source_bind_group: wgpu::BindGroup,
+    /// one per level, sampling it
+    level_bind_groups: Vec<wgpu::BindGroup>,
+    prefilter_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl Bloom {
+    pub fn new(bundle: &GraphicsBundle, hdr_texture: &Texture) -> Self {
+        let device = bundle.device;
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Bloom Uniform Buffer"),
+            size: std::mem::size_of::<BloomUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("bloom_bind_group_layout"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Bloom Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/bloom.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+        let create = |label, entry_point, blend| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Texture::HDR_FORMAT,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let prefilter_pipeline = create("Bloom Prefilter Pipeline", "fs_prefilter", None);
+        let downsample_pipeline = create("Bloom Downsample Pipeline", "fs_downsample", None);
+        let upsample_pipeline = create("Bloom Upsample Pipeline", "fs_upsample", Some(additive));
+        let composite_pipeline = create("Bloom Composite Pipeline", "fs_composite", Some(additive));
+
+        let source_bind_group = Bloom::create_bind_group(device, &bind_group_layout, hdr_texture, &sampler, &uniform_buffer);
+        let mut bloom = Bloom {
+            levels: Vec::new(),
+            uniform_buffer,
+            sampler,
+            bind_group_layout,
+            source_bind_group,
+            level_bind_groups: Vec::new(),
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            composite_pipeline,
+        };
+        bloom.resize(bundle, hdr_texture);
+        bloom
+    }
+
+    /// Recreates the levels for the surface's size, `hdr_texture` is the new HDR target.
+    pub fn resize(&mut self, bundle: &GraphicsBundle, hdr_texture: &Texture) {
+        let device = bundle.device;
+        self.levels = Bloom::level_sizes(bundle.config.width, bundle.config.height).into_iter()
+            .map(|(width, height)| Bloom::create_level(device, width, height))
+            .collect();
+        self.source_bind_group = Bloom::create_bind_group(device, &self.bind_group_layout, hdr_texture, &self.sampler, &self.uniform_buffer);
+        self.level_bind_groups = self.levels.iter()
+            .map(|level| Bloom::create_bind_group(device, &self.bind_group_layout, level, &self.sampler, &self.uniform_buffer))
+            .collect();
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, settings: &BloomSettings) {
+        let uniform = BloomUniform {
+            threshold: settings.threshold,
+            knee: settings.threshold * settings.soft_threshold,
+            intensity: settings.intensity,
+            radius: settings.radius,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Blurs the bright parts of `hdr_target` and adds them back onto it.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, hdr_target: &wgpu::TextureView) {
+        let Some(first) = self.levels.first() else {
+            return;
+        };
+        Bloom::pass(encoder, "Bloom Prefilter Pass", &first.view, &self.prefilter_pipeline, &self.source_bind_group, true);
+        for (index, level) in self.levels.iter().enumerate().skip(1) {
+            Bloom::pass(encoder, "Bloom Downsample Pass", &level.view, &self.downsample_pipeline, &self.level_bind_groups[index - 1], true);
+        }
+        for index in (1..self.levels.len()).rev() {
+            Bloom::pass(encoder, "Bloom Upsample Pass", &self.levels[index - 1].view, &self.upsample_pipeline, &self.level_bind_groups[index], false);
+        }
+        Bloom::pass(encoder, "Bloom Composite Pass", hdr_target, &self.composite_pipeline, &self.level_bind_groups[0], false);
+    }
+
+    fn pass(encoder: &mut wgpu::CommandEncoder, label: &str, target: &wgpu::TextureView, pipeline: &wgpu::RenderPipeline, bind_group: &wgpu::BindGroup, clear: bool) {
+        let load = match clear {
+            true => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            false => wgpu::LoadOp::Load,
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Halves the size until `MAX_LEVELS` or until the smaller side would drop below a few texels.
+    fn level_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+        let mut sizes = Vec::new();
+        let (mut width, mut height) = (width / 2, height / 2);
+        while sizes.len() < MAX_LEVELS && width.min(height) >= 4 {
+            sizes.push((width, height));
+            width /= 2;
+            height /= 2;
+        }
+        sizes
+    }
+
+    fn create_level(device: &wgpu::Device, width: u32, height: u32) -> Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("bloom_level"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        Texture { texture, view, sampler, bind_group: None }
+    }
+
+    fn create_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, source: &Texture, sampler: &wgpu::Sampler, uniform_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&source.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(sampler) },
+                wgpu::BindGroupEntry { binding: 2, resource: uniform_buffer.as_entire_binding() },
+            ],
+            label: Some("bloom_bind_group"),
+        })
+    }
+}
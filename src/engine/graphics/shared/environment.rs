@@ -0,0 +1,372 @@
+use wgpu::util::DeviceExt;
+use crate::graphics::{GraphicsBundle, Texture};
+
+/// Per pass parameters of `environment.wgsl`, one aligned slot per face and mip level.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BakeParams {
+    face: u32,
+    roughness: f32,
+    source_size: f32,
+    source_level: f32,
+}
+
+/// Image based lighting baked on the GPU from an environment cube map, bound at group 3 of the universal pipeline.
+/// The irradiance map holds the diffuse light arriving around each normal,
+/// the specular map the reflected environment blurred for a higher roughness at each mip level.
+#[derive(Debug)]
+pub struct EnvironmentMaps {
+    pub irradiance: Texture,
+    pub specular: Texture,
+}
+
+impl EnvironmentMaps {
+    pub const IRRADIANCE_SIZE: u32 = 32;
+    pub const SPECULAR_SIZE: u32 = 128;
+    /// roughness goes from 0 at the first level to 1 at the last
+    pub const SPECULAR_MIPS: u32 = 5;
+    pub const BRDF_LUT_SIZE: u32 = 256;
+    pub const BRDF_LUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+    /// Name of the white cube map registered by the renderer, lighting every surface with the ambient light.
+    pub const DEFAULT: &'static str = "Default Environment";
+
+    /// `size` is the number of texels per side of `cubemap`.
+    pub fn from_cubemap(bundle: &GraphicsBundle, cubemap: &Texture, size: u32, label: &str) -> Self {
+        let source = EnvironmentMaps::mipmapped(bundle, cubemap, size);
+        let irradiance = EnvironmentMaps::bake(bundle, &source, size, "fs_irradiance", EnvironmentMaps::IRRADIANCE_SIZE, 1, &format!("{} Irradiance", label));
+        let specular = EnvironmentMaps::bake(bundle, &source, size, "fs_prefilter", EnvironmentMaps::SPECULAR_SIZE, EnvironmentMaps::SPECULAR_MIPS, &format!("{} Specular", label));
+        EnvironmentMaps { irradiance, specular }
+    }
+
+    /// Lookup table of the specular lobe's response, independent of the environment so it is made once.
+    pub fn brdf_lut(bundle: &GraphicsBundle) -> Texture {
+        let device = bundle.device;
+        let size = EnvironmentMaps::BRDF_LUT_SIZE;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("brdf_lut"),
+            size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: EnvironmentMaps::BRDF_LUT_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("BRDF LUT Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = EnvironmentMaps::create_pipeline(device, &layout, "fs_brdf", EnvironmentMaps::BRDF_LUT_FORMAT);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("BRDF LUT Encoder"),
+        });
+        {
+            let mut render_pass = EnvironmentMaps::begin_pass(&mut encoder, &view);
+            render_pass.set_pipeline(&pipeline);
+            render_pass.draw(0..3, 0..1);
+        }
+        bundle.queue.submit(std::iter::once(encoder.finish()));
+
+        Texture { texture, view, sampler, bind_group: None }
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture(0, wgpu::TextureViewDimension::Cube),
+                texture(1, wgpu::TextureViewDimension::Cube),
+                texture(2, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("environment_bind_group_layout"),
+        })
+    }
+
+    /// Blends between the specular map's roughness levels, one is shared by every environment.
+    pub fn sampler(device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        })
+    }
+
+    /// The maps usually come from the asset manager, see `AssetManager::load_environment`.
+    pub fn bind_group(device: &wgpu::Device, sampler: &wgpu::Sampler, irradiance: &Texture, specular: &Texture, brdf_lut: &Texture) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &EnvironmentMaps::bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&irradiance.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&specular.view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&brdf_lut.view) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(sampler) },
+            ],
+            label: Some("environment_bind_group"),
+        })
+    }
+
+    /// Renders `entry_point` of `environment.wgsl` into every face and mip level of a new cube map.
+    /// `source` is read with a filter between its mip levels, `source_size` texels per side at the first one.
+    fn bake(bundle: &GraphicsBundle, source: &Texture, source_size: u32, entry_point: &str, size: u32, mip_level_count: u32, label: &str) -> Texture {
+        let device = bundle.device;
+        let mut params = Vec::new();
+        for mip in 0..mip_level_count {
+            let roughness = match mip_level_count {
+                1 => 0.0,
+                _ => mip as f32 / (mip_level_count - 1) as f32,
+            };
+            for face in 0..6 {
+                params.push(BakeParams { face, roughness, source_size: source_size as f32, source_level: 0.0 });
+            }
+        }
+        let bind_group_layout = EnvironmentMaps::bake_bind_group_layout(device);
+        let (params_buffer, stride) = EnvironmentMaps::params_buffer(device, &params);
+        let bind_group = EnvironmentMaps::bake_bind_group(device, &bind_group_layout, source, &params_buffer);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Environment Bake Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = EnvironmentMaps::create_pipeline(device, &layout, entry_point, Texture::HDR_FORMAT);
+
+        let texture = Texture::create_cube_texture(device, size, mip_level_count, Texture::HDR_FORMAT, label);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Environment Bake Encoder"),
+        });
+        for mip in 0..mip_level_count {
+            for face in 0..6 {
+                let face_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_mip_level: mip,
+                    mip_level_count: std::num::NonZeroU32::new(1),
+                    base_array_layer: face,
+                    array_layer_count: std::num::NonZeroU32::new(1),
+                    ..Default::default()
+                });
+                let mut render_pass = EnvironmentMaps::begin_pass(&mut encoder, &face_view);
+                render_pass.set_pipeline(&pipeline);
+                render_pass.set_bind_group(0, &bind_group, &[(mip * 6 + face) * stride]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+        bundle.queue.submit(std::iter::once(encoder.finish()));
+
+        Texture::from_cube_texture(device, texture)
+    }
+
+    /// HDR copy of `cubemap` with a full mip chain, each level averaging the one above it.
+    /// The prefilter reads the level matching the area a sample stands for, a few bright texels
+    /// picked by the sparse samples of a rough lobe would otherwise show as fireflies.
+    fn mipmapped(bundle: &GraphicsBundle, cubemap: &Texture, size: u32) -> Texture {
+        let device = bundle.device;
+        let mip_level_count = 32 - size.leading_zeros();
+        let texture = Texture::create_cube_texture(device, size, mip_level_count, Texture::HDR_FORMAT, "environment_mipmaps");
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let mipmaps = Texture { texture, view, sampler: EnvironmentMaps::sampler(device), bind_group: None };
+
+        let mut params = Vec::new();
+        for level in 0..mip_level_count {
+            for face in 0..6 {
+                params.push(BakeParams { face, roughness: 0.0, source_size: size as f32, source_level: level.saturating_sub(1) as f32 });
+            }
+        }
+        let bind_group_layout = EnvironmentMaps::bake_bind_group_layout(device);
+        let (params_buffer, stride) = EnvironmentMaps::params_buffer(device, &params);
+        // the first level is read from the original, the others from the level above them
+        let bind_groups = [
+            EnvironmentMaps::bake_bind_group(device, &bind_group_layout, cubemap, &params_buffer),
+            EnvironmentMaps::bake_bind_group(device, &bind_group_layout, &mipmaps, &params_buffer),
+        ];
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Environment Mipmap Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = EnvironmentMaps::create_pipeline(device, &layout, "fs_downsample", Texture::HDR_FORMAT);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Environment Mipmap Encoder"),
+        });
+        // rendered into a scratch texture and copied over like `Texture::generate_mipmaps`,
+        // the cube is sampled while its levels are written
+        let mut scratch = Vec::new();
+        for level in 0..mip_level_count {
+            let level_size = wgpu::Extent3d { width: (size >> level).max(1), height: (size >> level).max(1), depth_or_array_layers: 1 };
+            for face in 0..6 {
+                let target = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Environment Mipmap Scratch"),
+                    size: level_size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: Texture::HDR_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                });
+                let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+                {
+                    let mut render_pass = EnvironmentMaps::begin_pass(&mut encoder, &target_view);
+                    render_pass.set_pipeline(&pipeline);
+                    render_pass.set_bind_group(0, &bind_groups[level.min(1) as usize], &[(level * 6 + face) * stride]);
+                    render_pass.draw(0..3, 0..1);
+                }
+                encoder.copy_texture_to_texture(
+                    target.as_image_copy(),
+                    wgpu::ImageCopyTexture {
+                        texture: &mipmaps.texture,
+                        mip_level: level,
+                        origin: wgpu::Origin3d { x: 0, y: 0, z: face },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    level_size,
+                );
+                scratch.push(target);
+            }
+        }
+        bundle.queue.submit(std::iter::once(encoder.finish()));
+        mipmaps
+    }
+
+    fn bake_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<BakeParams>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("environment_bake_bind_group_layout"),
+        })
+    }
+
+    /// One aligned slot per pass, returns the buffer and the stride between the slots.
+    fn params_buffer(device: &wgpu::Device, params: &[BakeParams]) -> (wgpu::Buffer, u32) {
+        let stride = device.limits().min_uniform_buffer_offset_alignment;
+        let mut contents = vec![0u8; stride as usize * params.len()];
+        for (index, slot) in params.iter().enumerate() {
+            let offset = index * stride as usize;
+            contents[offset..offset + std::mem::size_of::<BakeParams>()].copy_from_slice(bytemuck::bytes_of(slot));
+        }
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Environment Bake Buffer"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        (buffer, stride)
+    }
+
+    fn bake_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, source: &Texture, params: &wgpu::Buffer) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&source.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&source.sampler) },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: params,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<BakeParams>() as u64),
+                    }),
+                },
+            ],
+            label: Some("environment_bake_bind_group"),
+        })
+    }
+
+    fn create_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, entry_point: &str, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Environment Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/environment.wgsl").into()),
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Environment Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn begin_pass<'a>(encoder: &'a mut wgpu::CommandEncoder, target: &'a wgpu::TextureView) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Environment Bake Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+}
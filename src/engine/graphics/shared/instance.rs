@@ -1,12 +1,33 @@
-
-
+use glam::{Mat3, Mat4};
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
     pub model: [[f32; 4]; 4],
+    /// bit set of the `InstanceRaw` flag constants
+    pub flags: u32,
+    /// transforms normals, the inverse transpose of the model matrix so non uniform scale doesn't skew them
+    pub normal: [[f32; 3]; 3],
 }
 impl InstanceRaw {
+    pub const RECEIVE_SHADOWS: u32 = 1;
+
+    pub fn new(model: Mat4, flags: u32) -> Self {
+        // the cofactor matrix is the inverse transpose times the determinant, which the shader normalizes away,
+        // and it stays defined when a scale is zero. The sign keeps mirrored normals pointing out
+        let linear = Mat3::from_mat4(model);
+        let normal = Mat3::from_cols(
+            linear.y_axis.cross(linear.z_axis),
+            linear.z_axis.cross(linear.x_axis),
+            linear.x_axis.cross(linear.y_axis),
+        ) * linear.determinant().signum();
+        InstanceRaw {
+            model: model.to_cols_array_2d(),
+            flags,
+            normal: normal.to_cols_array_2d(),
+        }
+    }
+
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem;
         wgpu::VertexBufferLayout {
@@ -41,7 +62,68 @@ impl InstanceRaw {
                     shader_location: 8,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 17]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 23]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
             ],
         }
     }
 }
+
+/// Vertex buffer of `InstanceRaw`s reused between frames, grows when it runs out of space.
+#[derive(Debug)]
+pub struct InstanceBuffer {
+    pub buffer: wgpu::Buffer,
+    /// number of instances that fit in the buffer
+    pub capacity: usize,
+    label: String,
+}
+
+impl InstanceBuffer {
+    const INITIAL_CAPACITY: usize = 64;
+
+    pub fn new(device: &wgpu::Device, label: &str) -> Self {
+        InstanceBuffer {
+            buffer: InstanceBuffer::create_buffer(device, label, InstanceBuffer::INITIAL_CAPACITY),
+            capacity: InstanceBuffer::INITIAL_CAPACITY,
+            label: String::from(label),
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &str, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Uploads `instances` to the start of the buffer, reallocating if they don't fit.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: &[InstanceRaw]) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+            self.buffer = InstanceBuffer::create_buffer(device, &self.label, self.capacity);
+        }
+        if !instances.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::graphics::{GraphicsSettings, ShadowMaps, shadow::{PointShadow, MAX_POINT_SHADOWS}};
+
+/// One light as laid out in the light storage buffer of `pbr.wgsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightRaw {
+    pub position: [f32; 3],
+    pub kind: u32,
+    pub direction: [f32; 3],
+    pub range: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// cosines of the spot cone angles
+    pub inner_cos: f32,
+    pub outer_cos: f32,
+    /// first layer in the shadow maps, or the cube map for point lights, -1 when the light casts no shadows
+    pub shadow_index: i32,
+    pub shadow_bias: f32,
+    /// number of shadow map layers, the cascades of a directional light
+    pub shadow_count: u32,
+    pub _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightingUniform {
+    /// scale of the environment maps, the ambient color premultiplied by its intensity
+    /// or the intensity of the environment light
+    pub ambient: [f32; 3],
+    pub light_count: u32,
+    /// view depth where each directional shadow cascade ends
+    pub cascade_splits: [f32; 4],
+    pub view_position: [f32; 3],
+    pub _padding: u32,
+    pub view_forward: [f32; 3],
+    pub cascade_blend: f32,
+}
+
+/// Every light in the scene and their shadow maps, bound at group 2 of the universal pipeline.
+/// The storage buffer grows when there are more lights than fit.
+#[derive(Debug)]
+pub struct LightBuffer {
+    pub uniform_buffer: wgpu::Buffer,
+    pub storage_buffer: wgpu::Buffer,
+    /// number of lights that fit in the storage buffer
+    pub capacity: usize,
+    pub shadow_maps: ShadowMaps,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl LightBuffer {
+    const INITIAL_CAPACITY: usize = 16;
+    const POINT_SHADOW_BINDING: u32 = 5;
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    sample_type: wgpu::TextureSampleType::Depth,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+        ];
+        // one binding per point shadow cube map
+        for slot in 0..MAX_POINT_SHADOWS as u32 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: LightBuffer::POINT_SHADOW_BINDING + slot,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    sample_type: wgpu::TextureSampleType::Depth,
+                },
+                count: None,
+            });
+        }
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("light_bind_group_layout"),
+        })
+    }
+
+    pub fn new(device: &wgpu::Device, settings: &GraphicsSettings) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lighting Uniform Buffer"),
+            size: std::mem::size_of::<LightingUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let storage_buffer = LightBuffer::create_storage_buffer(device, LightBuffer::INITIAL_CAPACITY);
+        let shadow_maps = ShadowMaps::new(device, settings);
+        let bind_group = LightBuffer::create_bind_group(device, &uniform_buffer, &storage_buffer, &shadow_maps);
+        LightBuffer { uniform_buffer, storage_buffer, capacity: LightBuffer::INITIAL_CAPACITY, shadow_maps, bind_group }
+    }
+
+    fn create_storage_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Storage Buffer"),
+            size: (capacity * std::mem::size_of::<LightRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_bind_group(device: &wgpu::Device, uniform_buffer: &wgpu::Buffer, storage_buffer: &wgpu::Buffer, shadow_maps: &ShadowMaps) -> wgpu::BindGroup {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: storage_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: shadow_maps.matrix_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&shadow_maps.texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(&shadow_maps.texture.sampler),
+            },
+        ];
+        for (slot, map) in shadow_maps.point_maps.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: LightBuffer::POINT_SHADOW_BINDING + slot as u32,
+                resource: wgpu::BindingResource::TextureView(&map.texture.view),
+            });
+        }
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &LightBuffer::bind_group_layout(device),
+            entries: &entries,
+            label: Some("light_bind_group"),
+        })
+    }
+
+    /// Fits the point shadow cube maps to `lights` and uploads their face views.
+    pub fn write_point_shadows(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, lights: &[PointShadow]) {
+        if self.shadow_maps.resize_point_maps(device, lights) {
+            self.bind_group = LightBuffer::create_bind_group(device, &self.uniform_buffer, &self.storage_buffer, &self.shadow_maps);
+        }
+        self.shadow_maps.write_point_views(queue, lights);
+    }
+
+    /// Uploads `lights` and `uniform`, whose light count is filled in here.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, mut uniform: LightingUniform, lights: &[LightRaw]) {
+        if lights.len() > self.capacity {
+            self.capacity = lights.len().next_power_of_two();
+            self.storage_buffer = LightBuffer::create_storage_buffer(device, self.capacity);
+            self.bind_group = LightBuffer::create_bind_group(device, &self.uniform_buffer, &self.storage_buffer, &self.shadow_maps);
+        }
+        uniform.light_count = lights.len() as u32;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        if !lights.is_empty() {
+            queue.write_buffer(&self.storage_buffer, 0, bytemuck::cast_slice(lights));
+        }
+    }
+}
@@ -1,16 +1,273 @@
-use crate::{graphics::{GraphicsBundle, render_pipeline, Texture}, assets::asset_manager::AssetRef};
+use crate::{graphics::{GraphicsBundle, Texture, Color, SamplerSettings}, assets::{AssetManager, asset_manager::{AssetRef, AssetType, Error}}};
 
+/// Factors of a material as laid out in the material uniform of `pbr.wgsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialUniform {
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    /// 0 unless the alpha mode is `Mask`, nothing has a lower alpha
+    pub alpha_cutoff: f32,
+}
+
+/// How a material's alpha is used, like glTF's `alphaMode`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum AlphaMode {
+    /// Alpha is ignored.
+    #[default]
+    Opaque,
+    /// Pixels with an alpha below `cutoff` are discarded and the rest drawn opaque, e.g. foliage.
+    Mask { cutoff: f32 },
+    /// Blended over what's behind it, drawn after the opaque sprites from back to front without writing depth.
+    /// Needs a pipeline created with `transparent`, `Material::build` picks "Universal Transparent" over "Universal".
+    Blend,
+}
+
+/// Metallic/roughness material, every texture is multiplied by its factor.
+/// Missing textures fall back to the renderer's default textures so the factors are used as is.
+///
+/// The metallic/roughness texture follows glTF, roughness in green and metallic in blue,
+/// occlusion is read from the red channel.
 #[derive(Debug)]
 pub struct Material {
     pub name: String,
     pub render_pipeline: AssetRef,
-    pub diffuse: AssetRef,
+
+    pub base_color: Color,
+    pub base_color_texture: Option<AssetRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub metallic_roughness_texture: Option<AssetRef>,
+    pub normal_scale: f32,
+    pub normal_texture: Option<AssetRef>,
+    pub occlusion_strength: f32,
+    pub occlusion_texture: Option<AssetRef>,
+    pub emissive: Color,
+    pub emissive_texture: Option<AssetRef>,
+    pub alpha_mode: AlphaMode,
+    /// replaces the samplers the textures were loaded with
+    pub sampler: Option<SamplerSettings>,
+
+    /// created by `build`
+    pub uniform_buffer: Option<wgpu::Buffer>,
+    pub bind_group: Option<wgpu::BindGroup>,
 }
 
 impl Material {
-    pub fn new (render_pipeline: AssetRef, diffuse: AssetRef, name: &str) -> Self {
-        Material { name: String::from(name), render_pipeline: render_pipeline, diffuse }
+    /// Names of the fallback textures registered by the renderer.
+    pub const DEFAULT_WHITE: &'static str = "Default White";
+    pub const DEFAULT_NORMAL: &'static str = "Default Normal";
+    /// Names of the PBR pipelines registered by the renderer, the second one blends.
+    pub const UNIVERSAL: &'static str = "Universal";
+    pub const UNIVERSAL_TRANSPARENT: &'static str = "Universal Transparent";
+
+    pub fn new(render_pipeline: AssetRef, name: &str) -> Self {
+        Material {
+            name: String::from(name),
+            render_pipeline,
+            base_color: Color::WHITE,
+            base_color_texture: None,
+            metallic: 0.0,
+            roughness: 0.5,
+            metallic_roughness_texture: None,
+            normal_scale: 1.0,
+            normal_texture: None,
+            occlusion_strength: 1.0,
+            occlusion_texture: None,
+            emissive: Color::BLACK,
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            sampler: None,
+            uniform_buffer: None,
+            bind_group: None,
+        }
+    }
+
+    pub fn with_base_color(mut self, color: Color) -> Self {
+        self.base_color = color;
+        self
+    }
+
+    pub fn with_base_color_texture(mut self, texture: AssetRef) -> Self {
+        self.base_color_texture = Some(texture);
+        self
     }
 
-    
-}
\ No newline at end of file
+    pub fn with_metallic_roughness(mut self, metallic: f32, roughness: f32) -> Self {
+        self.metallic = metallic;
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn with_metallic_roughness_texture(mut self, texture: AssetRef) -> Self {
+        self.metallic_roughness_texture = Some(texture);
+        self
+    }
+
+    pub fn with_normal_texture(mut self, texture: AssetRef, scale: f32) -> Self {
+        self.normal_texture = Some(texture);
+        self.normal_scale = scale;
+        self
+    }
+
+    pub fn with_occlusion_texture(mut self, texture: AssetRef, strength: f32) -> Self {
+        self.occlusion_texture = Some(texture);
+        self.occlusion_strength = strength;
+        self
+    }
+
+    pub fn with_emissive(mut self, color: Color) -> Self {
+        self.emissive = color;
+        self
+    }
+
+    /// Multiplied by the emissive color, which is black by default.
+    pub fn with_emissive_texture(mut self, texture: AssetRef) -> Self {
+        self.emissive_texture = Some(texture);
+        self
+    }
+
+    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    pub fn to_uniform(&self) -> MaterialUniform {
+        MaterialUniform {
+            base_color: self.base_color.into(),
+            emissive: self.emissive.into(),
+            metallic: self.metallic,
+            roughness: self.roughness,
+            normal_scale: self.normal_scale,
+            occlusion_strength: self.occlusion_strength,
+            alpha_cutoff: match self.alpha_mode {
+                AlphaMode::Mask { cutoff } => cutoff,
+                _ => 0.0,
+            },
+        }
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
+        // base color, metallic/roughness, normal, occlusion and emissive, each followed by its sampler
+        for slot in 0..5 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 1 + slot * 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 + slot * 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("material_bind_group_layout"),
+        })
+    }
+
+    /// Creates the uniform buffer and bind group, every texture must already be in the asset manager.
+    /// Changing the textures, sampler or alpha mode afterwards needs another `build`.
+    pub fn build(mut self, bundle: &GraphicsBundle, asset_manager: &AssetManager) -> Result<Self, Error> {
+        // the universal pipelines only differ in blending, use the one matching the alpha mode
+        if matches!(self.render_pipeline.name.as_deref(), Some(Material::UNIVERSAL) | Some(Material::UNIVERSAL_TRANSPARENT)) {
+            let name = match self.alpha_mode {
+                AlphaMode::Blend => Material::UNIVERSAL_TRANSPARENT,
+                _ => Material::UNIVERSAL,
+            };
+            self.render_pipeline = AssetRef::new(None, Some(name.to_string()), AssetType::RenderPipeline);
+        }
+        let textures = [
+            Material::texture_or(asset_manager, &self.base_color_texture, Material::DEFAULT_WHITE)?,
+            Material::texture_or(asset_manager, &self.metallic_roughness_texture, Material::DEFAULT_WHITE)?,
+            Material::texture_or(asset_manager, &self.normal_texture, Material::DEFAULT_NORMAL)?,
+            Material::texture_or(asset_manager, &self.occlusion_texture, Material::DEFAULT_WHITE)?,
+            Material::texture_or(asset_manager, &self.emissive_texture, Material::DEFAULT_WHITE)?,
+        ];
+
+        let uniform_buffer = bundle.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Material Uniform Buffer"),
+            size: std::mem::size_of::<MaterialUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        bundle.queue.write_buffer(&uniform_buffer, 0, bytemuck::cast_slice(&[self.to_uniform()]));
+
+        let sampler_override = self.sampler.as_ref().map(|settings| Texture::create_sampler(bundle, settings));
+
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ];
+        for (slot, texture) in textures.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1 + slot as u32 * 2,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + slot as u32 * 2,
+                resource: wgpu::BindingResource::Sampler(sampler_override.as_ref().unwrap_or(&texture.sampler)),
+            });
+        }
+        let bind_group = bundle.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &Material::bind_group_layout(bundle.device),
+            entries: &entries,
+            label: Some("material_bind_group"),
+        });
+
+        self.uniform_buffer = Some(uniform_buffer);
+        self.bind_group = Some(bind_group);
+        Ok(self)
+    }
+
+    /// Uploads the factors again after they changed.
+    pub fn write(&self, queue: &wgpu::Queue) {
+        if let Some(buffer) = &self.uniform_buffer {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[self.to_uniform()]));
+        }
+    }
+
+    /// True when one of the textures is the one registered as `name`.
+    pub fn uses_texture(&self, name: &str) -> bool {
+        [&self.base_color_texture, &self.metallic_roughness_texture, &self.normal_texture, &self.occlusion_texture, &self.emissive_texture]
+            .into_iter()
+            .any(|texture| texture.as_ref().and_then(|texture| texture.name.as_deref()) == Some(name))
+    }
+
+    fn texture_or<'a>(asset_manager: &'a AssetManager, texture: &Option<AssetRef>, default: &str) -> Result<&'a Texture, Error> {
+        match texture {
+            Some(texture) => asset_manager.get_texture(texture.clone()),
+            None => asset_manager.get_texture(AssetRef::new(None, Some(default.to_string()), AssetType::Texture)),
+        }
+    }
+}
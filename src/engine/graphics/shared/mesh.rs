@@ -1,4 +1,7 @@
-use crate::graphics::{Material, GraphicsBundle, Vertex};
+use std::ops::Range;
+
+use crate::{graphics::{Material, GraphicsBundle, Vertex, util::tangents}, spatial::Aabb};
+use glam::Vec3;
 use wgpu::util::DeviceExt;
 
 #[derive(Debug)]
@@ -7,14 +10,25 @@ pub struct Mesh {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub element_count: u32,
-    
+    /// local space bounds of the vertices
+    pub bounds: Aabb,
 }
 
 
 impl Mesh {
 
+    /// Generates the tangents of `vertices` before uploading them, see `new_with_tangents` to keep existing ones.
     pub fn new(bundle: &GraphicsBundle, name: &str, vertices: &[Vertex], indices: &[u32]) -> Self {
+        let mut vertices = vertices.to_vec();
+        if !tangents::generate_tangents(&mut vertices, indices) {
+            eprintln!("Mesh : cannot generate tangents for {:?} without usable normals and uvs, its normal maps won't apply", name);
+        }
+        Mesh::new_with_tangents(bundle, name, &vertices, indices)
+    }
+
+    pub fn new_with_tangents(bundle: &GraphicsBundle, name: &str, vertices: &[Vertex], indices: &[u32]) -> Self {
         let element_count = indices.len() as u32;
+        let bounds = Aabb::from_points(vertices.iter().map(|vertex| Vec3::from(vertex.position)));
         Mesh {
             name: String::from(name),
             vertex_buffer: bundle.device.create_buffer_init(
@@ -32,8 +46,22 @@ impl Mesh {
                 }
             ),
             element_count,
+            bounds,
         }
     }
     
     
-}
\ No newline at end of file
+}
+
+pub trait DrawMesh<'a> {
+    /// Draws `instances` from the instance buffer currently bound to slot 1.
+    fn draw_mesh_batch(&mut self, mesh: &'a Mesh, instances: Range<u32>);
+}
+
+impl<'a> DrawMesh<'a> for wgpu::RenderPass<'a> {
+    fn draw_mesh_batch(&mut self, mesh: &'a Mesh, instances: Range<u32>) {
+        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.draw_indexed(0..mesh.element_count, 0, instances);
+    }
+}
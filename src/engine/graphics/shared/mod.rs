@@ -3,4 +3,13 @@ pub mod mesh;
 pub mod model;
 pub mod vertex;
 pub mod texture;
-pub mod instance;
\ No newline at end of file
+pub mod instance;
+pub mod light;
+pub mod shadow;
+pub mod tonemapping;
+pub mod bloom;
+pub mod post_process;
+pub mod render_target;
+pub mod viewport_clear;
+pub mod skybox;
+pub mod environment;
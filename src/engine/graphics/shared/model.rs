@@ -60,13 +60,11 @@ impl Model {
     pub fn to_instance_raw(transform: &Transform) -> InstanceRaw {
         
         let matrix = Mat4::from_scale_rotation_translation(
-            Vec3::new(1.0, 1.0, 1.0), // scale
+            transform.scale, // scale
             transform.rotation, // rotation
             transform.position // position
         );
-        InstanceRaw {
-            model: matrix.to_cols_array_2d(),
-        }
+        InstanceRaw::new(matrix, InstanceRaw::RECEIVE_SHADOWS)
     }
 
     
@@ -0,0 +1,500 @@
+use crate::graphics::{GraphicsBundle, Texture, Color};
+
+/// Prepended to the source of every effect, see the file for what an effect gets.
+pub const PRELUDE: &str = include_str!("../shaders/post/prelude.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostProcessUniform {
+    resolution: [f32; 2],
+    texel_size: [f32; 2],
+    time: f32,
+    frame: u32,
+    srgb_target: u32,
+    _padding: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct VignetteParams {
+    pub color: [f32; 4],
+    pub intensity: f32,
+    /// distance from the center where the darkening starts, 1 is a corner
+    pub radius: f32,
+    pub smoothness: f32,
+    pub _padding: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ChromaticAberrationParams {
+    /// offset of the red and blue channels at the screen's edge, as a fraction of the screen
+    pub intensity: f32,
+    pub _padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FilmGrainParams {
+    pub intensity: f32,
+    /// size of a grain in pixels
+    pub size: f32,
+    pub _padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorGradingParams {
+    /// texels along each axis of the lookup table
+    pub size: f32,
+    /// blend between the original and the graded color
+    pub contribution: f32,
+    pub _padding: [f32; 2],
+}
+
+/// A fullscreen effect in the post-processing stack, drawn with the output of the effect before it.
+#[derive(Debug)]
+pub struct PostEffect {
+    pub name: String,
+    pub enabled: bool,
+    pub pipeline: wgpu::RenderPipeline,
+    pub params_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl PostEffect {
+    /// `source` is WGSL appended to the prelude in `shaders/post/prelude.wgsl`, implementing `fs_main`.
+    /// `params` is bound as the uniform in group 1 binding 0, `texture` in binding 1.
+    pub fn new<T: bytemuck::Pod>(bundle: &GraphicsBundle, name: &str, source: &str, params: &T, texture: Option<&Texture>) -> Self {
+        let device = bundle.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", PRELUDE, source).into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Effect Pipeline Layout"),
+            bind_group_layouts: &[&PostProcessStack::input_layout(device), &PostEffect::bind_group_layout(device)],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(name),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: bundle.config.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // uniforms are at least 16 bytes and grow in steps of 16
+        let params_size = (std::mem::size_of::<T>() as u64).max(16).div_ceil(16) * 16;
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Effect Params Buffer"),
+            size: params_size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        bundle.queue.write_buffer(&params_buffer, 0, bytemuck::bytes_of(params));
+
+        let placeholder;
+        let texture = match texture {
+            Some(texture) => texture,
+            None => {
+                placeholder = Texture::from_color(bundle, [255, 255, 255, 255], "post_effect_placeholder").unwrap();
+                &placeholder
+            }
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &PostEffect::bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&texture.view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&texture.sampler) },
+            ],
+            label: Some("post_effect_bind_group"),
+        });
+
+        PostEffect { name: String::from(name), enabled: true, pipeline, params_buffer, bind_group }
+    }
+
+    pub fn fxaa(bundle: &GraphicsBundle) -> Self {
+        PostEffect::new(bundle, "FXAA", include_str!("../shaders/post/fxaa.wgsl"), &[0.0f32; 4], None)
+    }
+
+    pub fn vignette(bundle: &GraphicsBundle, color: Color, intensity: f32, radius: f32, smoothness: f32) -> Self {
+        let params = VignetteParams { color: color.into(), intensity, radius, smoothness, _padding: 0.0 };
+        PostEffect::new(bundle, "Vignette", include_str!("../shaders/post/vignette.wgsl"), &params, None)
+    }
+
+    pub fn chromatic_aberration(bundle: &GraphicsBundle, intensity: f32) -> Self {
+        let params = ChromaticAberrationParams { intensity, _padding: [0.0; 3] };
+        PostEffect::new(bundle, "Chromatic Aberration", include_str!("../shaders/post/chromatic_aberration.wgsl"), &params, None)
+    }
+
+    pub fn film_grain(bundle: &GraphicsBundle, intensity: f32, size: f32) -> Self {
+        let params = FilmGrainParams { intensity, size, _padding: [0.0; 2] };
+        PostEffect::new(bundle, "Film Grain", include_str!("../shaders/post/film_grain.wgsl"), &params, None)
+    }
+
+    /// `lut` is a strip of `size` slices of `size` x `size` texels, red increasing left to right
+    /// inside a slice, green top to bottom and blue from slice to slice.
+    /// Load it with `TextureSettings::linear()` and without mipmaps so it's read as stored.
+    pub fn color_grading(bundle: &GraphicsBundle, lut: &Texture, size: u32, contribution: f32) -> Self {
+        let params = ColorGradingParams { size: size as f32, contribution, _padding: [0.0; 2] };
+        PostEffect::new(bundle, "Color Grading", include_str!("../shaders/post/color_grading.wgsl"), &params, Some(lut))
+    }
+
+    /// Replaces the parameters, `T` has to match the type the effect was created with.
+    pub fn set_params<T: bytemuck::Pod>(&self, queue: &wgpu::Queue, params: &T) {
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(params));
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("post_effect_bind_group_layout"),
+        })
+    }
+}
+
+/// Effects drawn in order after tonemapping, each one reading the previous one's output
+/// from one of two targets the size of the surface and writing into the other.
+/// The last enabled effect draws onto the surface.
+#[derive(Debug)]
+pub struct PostProcessStack {
+    pub effects: Vec<PostEffect>,
+    pub targets: [Texture; 2],
+    /// the scene's depth, copied out of the depth buffer when there are effects to draw
+    pub depth: Texture,
+    pub uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    /// reading `targets[0]` and `targets[1]`
+    input_bind_groups: [wgpu::BindGroup; 2],
+    depth_copy_pipeline: wgpu::RenderPipeline,
+    depth_copy_multisampled_pipeline: wgpu::RenderPipeline,
+    depth_copy_bind_group: wgpu::BindGroup,
+}
+
+impl PostProcessStack {
+    pub fn new(bundle: &GraphicsBundle, depth_texture: &Texture) -> Self {
+        let device = bundle.device;
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Process Uniform Buffer"),
+            size: std::mem::size_of::<PostProcessUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let targets = PostProcessStack::create_targets(bundle);
+        let depth = PostProcessStack::create_depth(bundle);
+        let input_bind_groups = PostProcessStack::create_input_bind_groups(device, &targets, &depth, &sampler, &uniform_buffer);
+        let depth_copy_pipeline = PostProcessStack::create_depth_copy_pipeline(device, false);
+        let depth_copy_multisampled_pipeline = PostProcessStack::create_depth_copy_pipeline(device, true);
+        let depth_copy_bind_group = PostProcessStack::create_depth_copy_bind_group(bundle, depth_texture);
+
+        PostProcessStack {
+            effects: Vec::new(),
+            targets,
+            depth,
+            uniform_buffer,
+            sampler,
+            input_bind_groups,
+            depth_copy_pipeline,
+            depth_copy_multisampled_pipeline,
+            depth_copy_bind_group,
+        }
+    }
+
+    /// Adds an effect after the others.
+    pub fn push(&mut self, effect: PostEffect) {
+        self.effects.push(effect);
+    }
+
+    pub fn insert(&mut self, index: usize, effect: PostEffect) {
+        self.effects.insert(index, effect);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<PostEffect> {
+        let index = self.effects.iter().position(|effect| effect.name == name)?;
+        Some(self.effects.remove(index))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PostEffect> {
+        self.effects.iter().find(|effect| effect.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    /// Where the tonemapped image goes when there are effects to draw, the surface otherwise.
+    pub fn input_view(&self) -> Option<&wgpu::TextureView> {
+        match self.effects.iter().any(|effect| effect.enabled) {
+            true => Some(&self.targets[0].view),
+            false => None,
+        }
+    }
+
+    /// Recreates the targets at the surface's size, `depth_texture` is the renderer's new depth buffer.
+    pub fn resize(&mut self, bundle: &GraphicsBundle, depth_texture: &Texture) {
+        self.targets = PostProcessStack::create_targets(bundle);
+        self.depth = PostProcessStack::create_depth(bundle);
+        self.input_bind_groups = PostProcessStack::create_input_bind_groups(bundle.device, &self.targets, &self.depth, &self.sampler, &self.uniform_buffer);
+        self.depth_copy_bind_group = PostProcessStack::create_depth_copy_bind_group(bundle, depth_texture);
+    }
+
+    /// `time` is in seconds.
+    pub fn write(&self, bundle: &GraphicsBundle, time: f32, frame: u32) {
+        let (width, height) = (bundle.config.width as f32, bundle.config.height as f32);
+        let uniform = PostProcessUniform {
+            resolution: [width, height],
+            texel_size: [1.0 / width, 1.0 / height],
+            time,
+            frame,
+            srgb_target: bundle.config.format.describe().srgb as u32,
+            _padding: 0,
+        };
+        bundle.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Draws the enabled effects, starting from what was drawn into `input_view`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, sample_count: u32, surface_view: &wgpu::TextureView) {
+        let effects: Vec<&PostEffect> = self.effects.iter().filter(|effect| effect.enabled).collect();
+        if effects.is_empty() {
+            return;
+        }
+
+        let depth_copy_pipeline = match sample_count > 1 {
+            true => &self.depth_copy_multisampled_pipeline,
+            false => &self.depth_copy_pipeline,
+        };
+        PostProcessStack::pass(encoder, "Depth Copy Pass", &self.depth.view, depth_copy_pipeline, &[&self.depth_copy_bind_group]);
+
+        for (index, effect) in effects.iter().enumerate() {
+            let target = match index + 1 == effects.len() {
+                true => surface_view,
+                false => &self.targets[(index + 1) % 2].view,
+            };
+            PostProcessStack::pass(encoder, &effect.name, target, &effect.pipeline, &[&self.input_bind_groups[index % 2], &effect.bind_group]);
+        }
+    }
+
+    fn pass(encoder: &mut wgpu::CommandEncoder, label: &str, target: &wgpu::TextureView, pipeline: &wgpu::RenderPipeline, bind_groups: &[&wgpu::BindGroup]) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Group 0 of every effect, see `shaders/post/prelude.wgsl`.
+    pub fn input_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("post_process_input_bind_group_layout"),
+        })
+    }
+
+    fn depth_copy_layout(device: &wgpu::Device, multisampled: bool) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        // read as a float texture, GL can't load from depth textures
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("depth_copy_bind_group_layout"),
+        })
+    }
+
+    fn create_depth_copy_pipeline(device: &wgpu::Device, multisampled: bool) -> wgpu::RenderPipeline {
+        let source = match multisampled {
+            true => include_str!("../shaders/post/depth_copy_multisampled.wgsl"),
+            false => include_str!("../shaders/post/depth_copy.wgsl"),
+        };
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Depth Copy Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth Copy Pipeline Layout"),
+            bind_group_layouts: &[&PostProcessStack::depth_copy_layout(device, multisampled)],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Depth Copy Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::TextureFormat::R32Float.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_depth_copy_bind_group(bundle: &GraphicsBundle, depth_texture: &Texture) -> wgpu::BindGroup {
+        bundle.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &PostProcessStack::depth_copy_layout(bundle.device, bundle.settings.sample_count > 1),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&depth_texture.view) },
+            ],
+            label: Some("depth_copy_bind_group"),
+        })
+    }
+
+    fn create_targets(bundle: &GraphicsBundle) -> [Texture; 2] {
+        [
+            PostProcessStack::create_target(bundle, bundle.config.format, "post_process_target"),
+            PostProcessStack::create_target(bundle, bundle.config.format, "post_process_target"),
+        ]
+    }
+
+    fn create_depth(bundle: &GraphicsBundle) -> Texture {
+        PostProcessStack::create_target(bundle, wgpu::TextureFormat::R32Float, "post_process_depth")
+    }
+
+    fn create_target(bundle: &GraphicsBundle, format: wgpu::TextureFormat, label: &str) -> Texture {
+        let texture = bundle.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: bundle.config.width,
+                height: bundle.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = bundle.device.create_sampler(&wgpu::SamplerDescriptor::default());
+        Texture { texture, view, sampler, bind_group: None }
+    }
+
+    fn create_input_bind_groups(device: &wgpu::Device, targets: &[Texture; 2], depth: &Texture, sampler: &wgpu::Sampler, uniform_buffer: &wgpu::Buffer) -> [wgpu::BindGroup; 2] {
+        let layout = PostProcessStack::input_layout(device);
+        [&targets[0], &targets[1]].map(|target| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&target.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(sampler) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&depth.view) },
+                wgpu::BindGroupEntry { binding: 3, resource: uniform_buffer.as_entire_binding() },
+            ],
+            label: Some("post_process_input_bind_group"),
+        }))
+    }
+}
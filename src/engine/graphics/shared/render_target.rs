@@ -0,0 +1,35 @@
+use crate::graphics::{Texture, Tonemapper};
+
+/// What a camera rendering into a texture draws into before it's tonemapped onto the texture,
+/// sized to it instead of the surface.
+#[derive(Debug)]
+pub struct OffscreenTargets {
+    pub width: u32,
+    pub height: u32,
+    pub hdr_texture: Texture,
+    /// rendered into when MSAA is on, resolved into `hdr_texture`
+    pub msaa_texture: Option<Texture>,
+    pub depth_texture: Texture,
+    pub tonemapping_bind_group: wgpu::BindGroup,
+}
+
+impl OffscreenTargets {
+    pub fn new(device: &wgpu::Device, tonemapper: &Tonemapper, width: u32, height: u32, sample_count: u32) -> Self {
+        let hdr_texture = Texture::create_target(device, width, height, Texture::HDR_FORMAT, 1, "offscreen_hdr_texture");
+        let msaa_texture = match sample_count > 1 {
+            true => Some(Texture::create_target(device, width, height, Texture::HDR_FORMAT, sample_count, "offscreen_msaa_texture")),
+            false => None,
+        };
+        let depth_texture = Texture::create_target(device, width, height, Texture::DEPTH_FORMAT, sample_count, "offscreen_depth_texture");
+        let tonemapping_bind_group = tonemapper.offscreen_bind_group(device, &hdr_texture);
+        OffscreenTargets { width, height, hdr_texture, msaa_texture, depth_texture, tonemapping_bind_group }
+    }
+
+    /// The view the scene is drawn into and the one it's resolved into, if any.
+    pub fn color_views(&self) -> (&wgpu::TextureView, Option<&wgpu::TextureView>) {
+        match &self.msaa_texture {
+            Some(msaa) => (&msaa.view, Some(&self.hdr_texture.view)),
+            None => (&self.hdr_texture.view, None),
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use glam::{Mat4, Vec3};
+
+use crate::{
+    graphics::{GraphicsSettings, Texture, Vertex, InstanceRaw, Material},
+    components::{Camera, Transform},
+    spatial::Aabb,
+};
+
+pub const MAX_CASCADES: usize = 4;
+/// Point lights casting shadows at once, the lighting shader has a cube map binding for each.
+pub const MAX_POINT_SHADOWS: usize = 4;
+
+/// How the view is divided between the cascades of a directional light's shadow.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CascadeSplit {
+    /// Equally long cascades.
+    Uniform,
+    /// Cascades growing with distance, most resolution close to the camera.
+    Logarithmic,
+    /// Mix of both, `0.0` is uniform and `1.0` logarithmic.
+    Practical(f32),
+}
+
+/// A point light's shadow, the distance to the closest caster in every direction.
+#[derive(Debug)]
+pub struct PointShadowMap {
+    pub texture: Texture,
+    pub face_views: Vec<wgpu::TextureView>,
+    pub size: u32,
+}
+
+impl PointShadowMap {
+    pub fn new(device: &wgpu::Device, size: u32) -> Self {
+        let texture = Texture::create_depth_cube(device, size, "point_shadow_map");
+        let face_views = (0..6).map(|face| texture.layer_view(face)).collect();
+        PointShadowMap { texture, face_views, size }
+    }
+}
+
+/// A point light rendering its shadow this frame.
+#[derive(Debug, Copy, Clone)]
+pub struct PointShadow {
+    pub position: Vec3,
+    pub range: f32,
+    pub resolution: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointShadowView {
+    view_proj: [[f32; 4]; 4],
+    light_position: [f32; 3],
+    range: f32,
+}
+
+/// Depth array holding one shadow map per layer, plus what's needed to render into it.
+/// Layer `i` is rendered with `matrices[i]`, the lighting shader samples it with the same matrix.
+///
+/// Point lights render into their own cube maps instead, there are `MAX_POINT_SHADOWS` of them.
+#[derive(Debug)]
+pub struct ShadowMaps {
+    pub texture: Texture,
+    pub layer_views: Vec<wgpu::TextureView>,
+    /// `array<mat4x4<f32>>` read by the lighting shader
+    pub matrix_buffer: wgpu::Buffer,
+    /// the same matrices, one per dynamic offset for the shadow pass
+    pub view_buffer: wgpu::Buffer,
+    pub view_bind_group: wgpu::BindGroup,
+    pub view_stride: u32,
+    pub pipeline: wgpu::RenderPipeline,
+    /// discards the pixels of `Mask` materials below their cutoff, the material is bound to group 1
+    pub cutout_pipeline: wgpu::RenderPipeline,
+    pub size: u32,
+
+    pub point_maps: Vec<PointShadowMap>,
+    /// six face views per point shadow map, one per dynamic offset
+    pub point_view_buffer: wgpu::Buffer,
+    pub point_view_bind_group: wgpu::BindGroup,
+    pub point_pipeline: wgpu::RenderPipeline,
+    pub point_cutout_pipeline: wgpu::RenderPipeline,
+}
+
+impl ShadowMaps {
+    pub fn new(device: &wgpu::Device, settings: &GraphicsSettings) -> Self {
+        let layers = settings.max_shadow_maps.max(1);
+        let texture = Texture::create_depth_array(device, settings.shadow_map_size, layers, "shadow_maps");
+        let layer_views = (0..layers).map(|layer| texture.layer_view(layer)).collect();
+
+        let matrix_size = std::mem::size_of::<Mat4>() as u32;
+        let point_view_size = std::mem::size_of::<PointShadowView>() as u32;
+        let alignment = device.limits().min_uniform_buffer_offset_alignment;
+        let view_stride = point_view_size.div_ceil(alignment) * alignment;
+
+        let matrix_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Matrix Buffer"),
+            size: (layers * matrix_size) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let (view_buffer, view_layout, view_bind_group) =
+            ShadowMaps::create_views(device, "Shadow View Buffer", layers * view_stride, matrix_size);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shadow.wgsl").into()),
+        });
+        // pushes casters away from the light against shadow acne on slopes
+        let bias = wgpu::DepthBiasState {
+            constant: 2,
+            slope_scale: 2.0,
+            clamp: 0.0,
+        };
+        let material_layout = Material::bind_group_layout(device);
+        let pipeline = ShadowMaps::create_pipeline(device, "Shadow Pipeline", &[&view_layout], &shader, None, bias);
+        let cutout_pipeline = ShadowMaps::create_pipeline(device, "Shadow Cutout Pipeline", &[&view_layout, &material_layout], &shader, Some("fs_cutout"), bias);
+
+        let point_maps = (0..MAX_POINT_SHADOWS).map(|_| PointShadowMap::new(device, 1)).collect();
+        let (point_view_buffer, point_view_layout, point_view_bind_group) =
+            ShadowMaps::create_views(device, "Point Shadow View Buffer", MAX_POINT_SHADOWS as u32 * 6 * view_stride, point_view_size);
+        let point_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Point Shadow Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/point_shadow.wgsl").into()),
+        });
+        // the fragment shader writes the distance to the light as depth, the bias wouldn't apply
+        let point_pipeline = ShadowMaps::create_pipeline(device, "Point Shadow Pipeline", &[&point_view_layout], &point_shader, Some("fs_main"), wgpu::DepthBiasState::default());
+        let point_cutout_pipeline = ShadowMaps::create_pipeline(device, "Point Shadow Cutout Pipeline", &[&point_view_layout, &material_layout], &point_shader, Some("fs_cutout"), wgpu::DepthBiasState::default());
+
+        ShadowMaps {
+            texture,
+            layer_views,
+            matrix_buffer,
+            view_buffer,
+            view_bind_group,
+            view_stride,
+            pipeline,
+            cutout_pipeline,
+            size: settings.shadow_map_size,
+            point_maps,
+            point_view_buffer,
+            point_view_bind_group,
+            point_pipeline,
+            point_cutout_pipeline,
+        }
+    }
+
+    /// Uniform buffer read with dynamic offsets, `binding_size` bytes at a time.
+    fn create_views(device: &wgpu::Device, label: &str, size: u32, binding_size: u32) -> (wgpu::Buffer, wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(binding_size as u64),
+                },
+                count: None,
+            }],
+            label: Some("shadow_view_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(binding_size as u64),
+                }),
+            }],
+            label: Some("shadow_view_bind_group"),
+        });
+        (buffer, layout, bind_group)
+    }
+
+    fn create_pipeline(device: &wgpu::Device, label: &str, bind_group_layouts: &[&wgpu::BindGroupLayout], shader: &wgpu::ShaderModule, fragment_entry: Option<&str>, bias: wgpu::DepthBiasState) -> wgpu::RenderPipeline {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), InstanceRaw::desc()],
+            },
+            fragment: fragment_entry.map(|entry_point| wgpu::FragmentState {
+                module: shader,
+                entry_point,
+                targets: &[],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // sprites are single sided planes, both faces have to cast
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias,
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.layer_views.len()
+    }
+
+    /// Uploads the light space matrix of every shadow map rendered this frame.
+    pub fn write(&self, queue: &wgpu::Queue, matrices: &[Mat4]) {
+        if matrices.is_empty() {
+            return;
+        }
+        let matrices: Vec<[[f32; 4]; 4]> = matrices.iter()
+            .take(self.capacity())
+            .map(|matrix| matrix.to_cols_array_2d())
+            .collect();
+        queue.write_buffer(&self.matrix_buffer, 0, bytemuck::cast_slice(&matrices));
+
+        let stride = self.view_stride as usize;
+        let mut views = vec![0u8; matrices.len() * stride];
+        for (index, matrix) in matrices.iter().enumerate() {
+            let bytes = bytemuck::bytes_of(matrix);
+            views[index * stride..index * stride + bytes.len()].copy_from_slice(bytes);
+        }
+        queue.write_buffer(&self.view_buffer, 0, &views);
+    }
+
+    /// Dynamic offset into `view_bind_group` for shadow map `index`.
+    pub fn view_offset(&self, index: usize) -> u32 {
+        index as u32 * self.view_stride
+    }
+
+    /// Recreates the cube maps whose resolution changed, returns whether any was.
+    /// `lights[i]` renders into `point_maps[i]`.
+    pub fn resize_point_maps(&mut self, device: &wgpu::Device, lights: &[PointShadow]) -> bool {
+        let mut resized = false;
+        for (map, light) in self.point_maps.iter_mut().zip(lights.iter()) {
+            let size = light.resolution.max(1);
+            if map.size != size {
+                *map = PointShadowMap::new(device, size);
+                resized = true;
+            }
+        }
+        resized
+    }
+
+    /// Uploads the face views of every point shadow rendered this frame.
+    pub fn write_point_views(&self, queue: &wgpu::Queue, lights: &[PointShadow]) {
+        if lights.is_empty() {
+            return;
+        }
+        let stride = self.view_stride as usize;
+        let mut views = vec![0u8; lights.len().min(MAX_POINT_SHADOWS) * 6 * stride];
+        for (slot, light) in lights.iter().take(MAX_POINT_SHADOWS).enumerate() {
+            for (face, matrix) in point_face_matrices(light.position, light.range).iter().enumerate() {
+                let view = PointShadowView {
+                    view_proj: matrix.to_cols_array_2d(),
+                    light_position: light.position.into(),
+                    range: light.range,
+                };
+                let bytes = bytemuck::bytes_of(&view);
+                let offset = (slot * 6 + face) * stride;
+                views[offset..offset + bytes.len()].copy_from_slice(bytes);
+            }
+        }
+        queue.write_buffer(&self.point_view_buffer, 0, &views);
+    }
+
+    /// Dynamic offset into `point_view_bind_group` for one face of a point shadow map.
+    pub fn point_view_offset(&self, slot: usize, face: usize) -> u32 {
+        (slot * 6 + face) as u32 * self.view_stride
+    }
+}
+
+/// View projections of the six cube faces around a point light, in wgpu's face order (+X, -X, +Y, -Y, +Z, -Z).
+pub fn point_face_matrices(position: Vec3, range: f32) -> [Mat4; 6] {
+    // cube maps are addressed left handed, flipping y in clip space matches the rendered faces to the lookups
+    let projection = Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0))
+        * Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, (range * 0.001).max(0.01), range.max(0.02));
+    let faces = [
+        (Vec3::X, Vec3::NEG_Y),
+        (Vec3::NEG_X, Vec3::NEG_Y),
+        (Vec3::Y, Vec3::Z),
+        (Vec3::NEG_Y, Vec3::NEG_Z),
+        (Vec3::Z, Vec3::NEG_Y),
+        (Vec3::NEG_Z, Vec3::NEG_Y),
+    ];
+    faces.map(|(direction, up)| projection * Mat4::look_to_rh(position, direction, up))
+}
+
+/// An up vector that isn't parallel to `direction`.
+fn light_up(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 { Vec3::X } else { Vec3::Y }
+}
+
+/// Orthographic light space matrix fitting `corners` (e.g. a camera frustum), extended
+/// towards the light to include every caster in `caster_bounds`.
+///
+/// The region is fitted with a sphere and its center snapped to whole shadow map texels,
+/// so the shadows don't shimmer while the camera moves or turns.
+pub fn directional_shadow_matrix(direction: Vec3, corners: &[Vec3], caster_bounds: &Aabb, map_size: u32) -> Mat4 {
+    let direction = direction.normalize();
+    let view = Mat4::look_to_rh(Vec3::ZERO, direction, light_up(direction));
+
+    let center = corners.iter().copied().sum::<Vec3>() / corners.len() as f32;
+    let radius = corners.iter().map(|corner| corner.distance(center)).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let texel = radius * 2.0 / map_size.max(1) as f32;
+    let mut center = view.transform_point3(center);
+    center.x = (center.x / texel).floor() * texel;
+    center.y = (center.y / texel).floor() * texel;
+
+    // the light looks down -z, casters between the light and the fitted region have a larger z
+    let mut max_z = center.z + radius;
+    if !caster_bounds.is_empty() {
+        max_z = max_z.max(caster_bounds.transform(&view).max.z);
+    }
+    let min_z = center.z - radius;
+    let projection = Mat4::orthographic_rh(
+        center.x - radius, center.x + radius,
+        center.y - radius, center.y + radius,
+        -max_z, -min_z,
+    );
+    projection * view
+}
+
+/// View depths where each cascade ends, between `near` and `far`.
+pub fn cascade_splits(split: CascadeSplit, near: f32, far: f32, count: usize) -> Vec<f32> {
+    let near = near.max(0.001);
+    (1..=count).map(|index| {
+        let t = index as f32 / count as f32;
+        let uniform = near + (far - near) * t;
+        let logarithmic = near * (far / near).powf(t);
+        match split {
+            CascadeSplit::Uniform => uniform,
+            CascadeSplit::Logarithmic => logarithmic,
+            CascadeSplit::Practical(lambda) => logarithmic * lambda + uniform * (1.0 - lambda),
+        }
+    }).collect()
+}
+
+/// The part of the camera's view covered by directional light cascades.
+#[derive(Debug, Clone)]
+pub struct CascadeView {
+    /// the camera's frustum corners, near plane first
+    pub corners: [Vec3; 8],
+    pub position: Vec3,
+    pub forward: Vec3,
+    /// view depth where each cascade ends
+    pub splits: Vec<f32>,
+}
+
+impl CascadeView {
+    pub fn new(camera: &Camera, transform: &Transform, settings: &GraphicsSettings) -> Self {
+        let count = (settings.shadow_cascades as usize).clamp(1, MAX_CASCADES);
+        let far = camera.zfar.min(settings.shadow_distance);
+        CascadeView {
+            corners: camera.frustum_corners(transform),
+            position: transform.position,
+            forward: transform.forward(),
+            splits: cascade_splits(settings.cascade_split, camera.znear, far, count),
+        }
+    }
+
+    /// One light space matrix per cascade of a directional light. Each cascade also covers
+    /// the last `blend` fraction of the one before it, where the shader blends between the two.
+    pub fn shadow_matrices(&self, direction: Vec3, blend: f32, caster_bounds: &Aabb, map_size: u32) -> Vec<Mat4> {
+        let near_depth = (self.corners[0] - self.position).dot(self.forward);
+        let far_depth = (self.corners[4] - self.position).dot(self.forward);
+        let corners_at = |depth: f32| {
+            let t = (depth - near_depth) / (far_depth - near_depth);
+            [0, 1, 2, 3].map(|index| self.corners[index].lerp(self.corners[index + 4], t))
+        };
+
+        let mut matrices = Vec::with_capacity(self.splits.len());
+        let mut previous = 0.0;
+        let mut start = near_depth;
+        for &split in self.splits.iter() {
+            let mut points = corners_at(start).to_vec();
+            points.extend(corners_at(split));
+            matrices.push(directional_shadow_matrix(direction, &points, caster_bounds, map_size));
+
+            start = (split - (split - previous) * blend).max(near_depth);
+            previous = split;
+        }
+        matrices
+    }
+
+    /// Splits padded to the fixed size array in `LightingUniform`.
+    pub fn uniform_splits(&self) -> [f32; MAX_CASCADES] {
+        let mut splits = [f32::MAX; MAX_CASCADES];
+        for (target, split) in splits.iter_mut().zip(self.splits.iter()) {
+            *target = *split;
+        }
+        splits
+    }
+}
+
+/// Perspective light space matrix covering a spot light's cone.
+pub fn spot_shadow_matrix(position: Vec3, direction: Vec3, outer_angle: f32, range: f32) -> Mat4 {
+    let direction = direction.normalize();
+    let view = Mat4::look_to_rh(position, direction, light_up(direction));
+    let fovy = (outer_angle * 2.0).clamp(0.01, std::f32::consts::PI - 0.01);
+    let projection = Mat4::perspective_rh(fovy, 1.0, (range * 0.001).max(0.01), range.max(0.02));
+    projection * view
+}
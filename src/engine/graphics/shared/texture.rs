@@ -1,6 +1,97 @@
 use image::GenericImageView;
+use wgpu::util::DeviceExt;
 use crate::graphics::GraphicsBundle;
 
+/// How a texture is sampled.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SamplerSettings {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    /// 1 turns anisotropic filtering off, only applies when every filter is linear
+    /// and is capped by `GraphicsSettings::max_anisotropy`
+    pub anisotropy: u8,
+    /// color outside the texture for `AddressMode::ClampToBorder`, which falls back to
+    /// `ClampToEdge` when the device doesn't support it
+    pub border_color: Option<wgpu::SamplerBorderColor>,
+}
+
+impl SamplerSettings {
+    pub fn with_address_mode(mut self, address_mode: wgpu::AddressMode) -> Self {
+        self.address_mode_u = address_mode;
+        self.address_mode_v = address_mode;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self.mipmap_filter = filter;
+        self
+    }
+
+    pub fn with_anisotropy(mut self, anisotropy: u8) -> Self {
+        self.anisotropy = anisotropy;
+        self
+    }
+
+    pub fn with_border(mut self, color: wgpu::SamplerBorderColor) -> Self {
+        self.address_mode_u = wgpu::AddressMode::ClampToBorder;
+        self.address_mode_v = wgpu::AddressMode::ClampToBorder;
+        self.border_color = Some(color);
+        self
+    }
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        SamplerSettings {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy: 16,
+            border_color: None,
+        }
+    }
+}
+
+/// Options for creating a texture from an image.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextureSettings {
+    pub sampler: SamplerSettings,
+    /// downsamples the full mip chain on the GPU when the texture is created
+    pub generate_mipmaps: bool,
+    /// false for textures holding data rather than color, such as normal, metallic/roughness or occlusion maps
+    pub srgb: bool,
+}
+
+impl TextureSettings {
+    /// Settings for data textures, without the sRGB conversion.
+    pub fn linear() -> Self {
+        TextureSettings { srgb: false, ..Default::default() }
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn with_mipmaps(mut self, generate_mipmaps: bool) -> Self {
+        self.generate_mipmaps = generate_mipmaps;
+        self
+    }
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        TextureSettings { sampler: SamplerSettings::default(), generate_mipmaps: true, srgb: true }
+    }
+}
+
 #[derive(Debug)]
 pub struct Texture {
     pub texture: wgpu::Texture,
@@ -11,6 +102,10 @@ pub struct Texture {
 
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    /// Format the scene is rendered in before tonemapping.
+    pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    /// Format of the textures cameras render into.
+    pub const RENDER_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
 
     pub fn create_depth_texture(bundle: &GraphicsBundle, label: &str) -> Self {
         let size = wgpu::Extent3d {
@@ -22,7 +117,7 @@ impl Texture {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count: bundle.settings.sample_count,
             dimension: wgpu::TextureDimension::D2,
             format:Self::DEPTH_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -50,13 +145,367 @@ impl Texture {
         
     }
 
+    /// Multisampled HDR target the size of the surface, resolved into the HDR texture at the end of a pass.
+    pub fn create_multisampled_target(bundle: &GraphicsBundle, label: &str) -> Self {
+        let texture = bundle.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: bundle.config.width,
+                height: bundle.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: bundle.settings.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = bundle.device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self { texture, view, sampler, bind_group: None }
+    }
+
+    /// HDR color target the size of the surface, tonemapped onto the surface texture at the end of a frame.
+    pub fn create_hdr_target(bundle: &GraphicsBundle, label: &str) -> Self {
+        let texture = bundle.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: bundle.config.width,
+                height: bundle.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = bundle.device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self { texture, view, sampler, bind_group: None }
+    }
+
+    /// Color or depth target with its own size, for views that don't cover the surface.
+    pub fn create_target(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat, sample_count: u32, label: &str) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self { texture, view, sampler, bind_group: None }
+    }
+
+    /// Texture a camera renders into, sampled with linear filtering by the materials showing it.
+    pub fn create_render_texture(device: &wgpu::Device, width: u32, height: u32, label: &str) -> Self {
+        let mut texture = Texture::create_target(device, width, height, Self::RENDER_TEXTURE_FORMAT, 1, label);
+        texture.sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        texture
+    }
+
+    /// Cube map from six square images of the same size, in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cubemap_from_images(bundle: &GraphicsBundle, faces: &[image::DynamicImage], label: &str) -> Self {
+        let size = faces[0].dimensions().0;
+        let texture = Texture::create_cube_texture(bundle.device, size, 1, wgpu::TextureFormat::Rgba8UnormSrgb, label);
+        for (layer, face) in faces.iter().enumerate() {
+            bundle.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                },
+                &face.to_rgba8(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(4 * size),
+                    rows_per_image: std::num::NonZeroU32::new(size),
+                },
+                wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+            );
+        }
+        Texture::from_cube_texture(bundle.device, texture)
+    }
+
+    /// Cube map with `size` texels per side converted from an equirectangular image on the GPU,
+    /// in `HDR_FORMAT` so HDR images keep their range.
+    pub fn cubemap_from_equirectangular(bundle: &GraphicsBundle, image: &image::DynamicImage, size: u32, label: &str) -> Self {
+        let device = bundle.device;
+        let (width, height) = image.dimensions();
+        // 32 bit floats aren't filterable everywhere, the source is sampled with the nearest filter
+        let source = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("equirectangular_texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        bundle.queue.write_texture(
+            source.as_image_copy(),
+            bytemuck::cast_slice(image.to_rgba32f().as_raw()),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(16 * width),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        let source_view = source.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(4),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("equirectangular_bind_group_layout"),
+        });
+        // one face index per aligned slot
+        let face_stride = device.limits().min_uniform_buffer_offset_alignment;
+        let mut faces = vec![0u8; 6 * face_stride as usize];
+        for face in 0..6 {
+            let offset = face * face_stride as usize;
+            faces[offset..offset + 4].copy_from_slice(&(face as u32).to_le_bytes());
+        }
+        let face_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Equirectangular Face Buffer"),
+            contents: &faces,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&source_view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &face_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(4),
+                    }),
+                },
+            ],
+            label: Some("equirectangular_bind_group"),
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Equirectangular Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/equirectangular.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Equirectangular Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Equirectangular Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Self::HDR_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let texture = Texture::create_cube_texture(device, size, 1, Self::HDR_FORMAT, label);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Equirectangular Encoder"),
+        });
+        for face in 0..6 {
+            let face_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: face,
+                array_layer_count: std::num::NonZeroU32::new(1),
+                ..Default::default()
+            });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Equirectangular Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &face_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[face * face_stride]);
+            render_pass.draw(0..3, 0..1);
+        }
+        bundle.queue.submit(std::iter::once(encoder.finish()));
+
+        Texture::from_cube_texture(device, texture)
+    }
+
+    pub(crate) fn create_cube_texture(device: &wgpu::Device, size: u32, mip_level_count: u32, format: wgpu::TextureFormat, label: &str) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+    }
+
+    pub(crate) fn from_cube_texture(device: &wgpu::Device, texture: wgpu::Texture) -> Self {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self { texture, view, sampler, bind_group: None }
+    }
+
+    /// Square depth texture with `layers` layers, the view covers the whole array.
+    /// Used for shadow maps, the sampler compares depth like the one in `create_depth_texture`.
+    pub fn create_depth_array(device: &wgpu::Device, size: u32, layers: u32, label: &str) -> Self {
+        Texture::create_layered_depth(device, size, layers, wgpu::TextureViewDimension::D2Array, label)
+    }
+
+    /// Depth cube map, each face is a layer.
+    pub fn create_depth_cube(device: &wgpu::Device, size: u32, label: &str) -> Self {
+        Texture::create_layered_depth(device, size, 6, wgpu::TextureViewDimension::Cube, label)
+    }
+
+    fn create_layered_depth(device: &wgpu::Device, size: u32, layers: u32, dimension: wgpu::TextureViewDimension, label: &str) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(dimension),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(
+            &wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                compare: Some(wgpu::CompareFunction::LessEqual),
+                lod_min_clamp: -100.0,
+                lod_max_clamp: 100.0,
+                ..Default::default()
+            }
+        );
+
+        Self { texture, view, sampler, bind_group: None }
+    }
+
+    /// View of a single layer of an array texture, to render into it.
+    pub fn layer_view(&self, layer: u32) -> wgpu::TextureView {
+        self.texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: layer,
+            array_layer_count: std::num::NonZeroU32::new(1),
+            ..Default::default()
+        })
+    }
+
     pub fn from_bytes(
         bundle: &GraphicsBundle,
         bytes: &[u8], 
         label: &str
+    ) -> Result<Self, wgpu::Error> {
+        Self::from_bytes_with_settings(bundle, bytes, label, &TextureSettings::default())
+    }
+
+    pub fn from_bytes_with_settings(
+        bundle: &GraphicsBundle,
+        bytes: &[u8],
+        label: &str,
+        settings: &TextureSettings,
     ) -> Result<Self, wgpu::Error> {
         let img = image::load_from_memory(bytes).unwrap();
-        Self::from_image(bundle, &img, Some(label))
+        Self::from_image_with_settings(bundle, &img, Some(label), settings)
     }
 
     pub fn bind_group_layout(bundle: &GraphicsBundle) -> wgpu::BindGroupLayout {
@@ -91,10 +540,25 @@ impl Texture {
         
     }
 
+    /// 1x1 texture of a single color, used as a stand in for missing material textures.
+    pub fn from_color(bundle: &GraphicsBundle, color: [u8; 4], label: &str) -> Result<Self, wgpu::Error> {
+        let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(color)));
+        Self::from_image_with_settings(bundle, &img, Some(label), &TextureSettings::linear().with_mipmaps(false))
+    }
+
     pub fn from_image(
         bundle: &GraphicsBundle,
         img: &image::DynamicImage,
         label: Option<&str>
+    ) -> Result<Self, wgpu::Error> {
+        Self::from_image_with_settings(bundle, img, label, &TextureSettings::default())
+    }
+
+    pub fn from_image_with_settings(
+        bundle: &GraphicsBundle,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        settings: &TextureSettings,
     ) -> Result<Self, wgpu::Error> {
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
@@ -104,14 +568,16 @@ impl Texture {
             height: dimensions.1,
             depth_or_array_layers: 1,
         };
+        let format = if settings.srgb { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm };
+        let mip_level_count = if settings.generate_mipmaps { size.max_mips(wgpu::TextureDimension::D2) } else { 1 };
         let texture = bundle.device.create_texture(
             &wgpu::TextureDescriptor {
                 label,
                 size,
-                mip_level_count: 1,
+                mip_level_count,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             }
         );
@@ -132,18 +598,12 @@ impl Texture {
             size,
         );
 
+        if mip_level_count > 1 {
+            Texture::generate_mipmaps(bundle.device, bundle.queue, &texture, size, format, mip_level_count);
+        }
+
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = bundle.device.create_sampler(
-            &wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::Repeat,
-                address_mode_v: wgpu::AddressMode::Repeat,
-                address_mode_w: wgpu::AddressMode::Repeat,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Nearest,
-                mipmap_filter: wgpu::FilterMode::Nearest,
-                ..Default::default()
-            }
-        );
+        let sampler = Texture::create_sampler(bundle, &settings.sampler);
 
         let bind_group = Some(bundle.device.create_bind_group(
             &wgpu::BindGroupDescriptor {
@@ -164,4 +624,135 @@ impl Texture {
         
         Ok(Self { texture, view, sampler, bind_group })
     }
-}
\ No newline at end of file
+
+    pub fn create_sampler(bundle: &GraphicsBundle, settings: &SamplerSettings) -> wgpu::Sampler {
+        let linear = [settings.mag_filter, settings.min_filter, settings.mipmap_filter]
+            .iter().all(|filter| *filter == wgpu::FilterMode::Linear);
+        let anisotropy = settings.anisotropy.min(bundle.settings.max_anisotropy).min(16);
+        // wgpu only accepts powers of two
+        let anisotropy_clamp = match linear && anisotropy > 1 {
+            true => std::num::NonZeroU8::new(1 << (7 - anisotropy.leading_zeros())),
+            false => None,
+        };
+        let border_supported = bundle.device.features().contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER);
+        let address_mode = |mode: wgpu::AddressMode| match mode {
+            wgpu::AddressMode::ClampToBorder if !border_supported => wgpu::AddressMode::ClampToEdge,
+            mode => mode,
+        };
+        bundle.device.create_sampler(
+            &wgpu::SamplerDescriptor {
+                address_mode_u: address_mode(settings.address_mode_u),
+                address_mode_v: address_mode(settings.address_mode_v),
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: settings.mag_filter,
+                min_filter: settings.min_filter,
+                mipmap_filter: settings.mipmap_filter,
+                anisotropy_clamp,
+                border_color: settings.border_color.filter(|_| border_supported),
+                ..Default::default()
+            }
+        )
+    }
+
+    /// Fills mip levels 1 and up by downsampling each level from the one above it with linear filtering.
+    fn generate_mipmaps(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, size: wgpu::Extent3d, format: wgpu::TextureFormat, mip_level_count: u32) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mipmap Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/blit.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Mipmap Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let level_views: Vec<wgpu::TextureView> = (0..mip_level_count)
+            .map(|level| texture.create_view(&wgpu::TextureViewDescriptor {
+                base_mip_level: level,
+                mip_level_count: std::num::NonZeroU32::new(1),
+                ..Default::default()
+            }))
+            .collect();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Mipmap Encoder") });
+        // each level is rendered into a scratch texture and copied over, some backends can't
+        // render into one level of a texture while sampling another
+        let mut level_size = size;
+        // kept alive until the copies are submitted
+        let mut scratch = Vec::new();
+        for level in 1..mip_level_count {
+            level_size.width = (level_size.width / 2).max(1);
+            level_size.height = (level_size.height / 2).max(1);
+            let target = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Mipmap Scratch"),
+                size: level_size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            });
+            let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&level_views[level as usize - 1]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+                label: None,
+            });
+            {
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Mipmap Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+            encoder.copy_texture_to_texture(
+                target.as_image_copy(),
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                level_size,
+            );
+            scratch.push(target);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
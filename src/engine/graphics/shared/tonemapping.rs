@@ -0,0 +1,383 @@
+use wgpu::util::DeviceExt;
+
+use crate::graphics::{GraphicsBundle, GraphicsSettings, Texture};
+
+/// Curve mapping the exposed HDR color into the displayable range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tonemapping {
+    /// Clamps, everything above 1 is lost.
+    None,
+    Reinhard,
+    /// Filmic curve fitted to the ACES reference transforms, slightly contrasty.
+    Aces,
+    /// Desaturates highlights towards white instead of skewing their hue.
+    AgX,
+}
+
+impl Tonemapping {
+    fn to_raw(self) -> u32 {
+        match self {
+            Tonemapping::None => 0,
+            Tonemapping::Reinhard => 1,
+            Tonemapping::Aces => 2,
+            Tonemapping::AgX => 3,
+        }
+    }
+}
+
+/// How bright the HDR target is scaled before tonemapping, in exposure values,
+/// every step up doubles the brightness.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Exposure {
+    Manual(f32),
+    /// Adapts to the average luminance of the last frames like an eye would.
+    /// `min_ev` and `max_ev` are the log2 of the darkest and brightest average luminance adapted to,
+    /// `speed` how fast it adapts and `compensation` brightens or darkens the result.
+    Auto { min_ev: f32, max_ev: f32, speed: f32, compensation: f32 },
+}
+
+impl Exposure {
+    pub fn auto() -> Self {
+        Exposure::Auto { min_ev: -8.0, max_ev: 4.0, speed: 1.5, compensation: 0.0 }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemappingUniform {
+    exposure: f32,
+    curve: u32,
+    auto_exposure: u32,
+    encode_srgb: u32,
+    key_value: f32,
+    _padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ExposureParams {
+    min_log_luminance: f32,
+    inverse_log_luminance_range: f32,
+    log_luminance_range: f32,
+    adaptation: f32,
+    pixel_count: u32,
+    _padding: [u32; 3],
+}
+
+/// Luminance auto exposure maps the scene average to, middle grey.
+const KEY_VALUE: f32 = 0.18;
+const HISTOGRAM_BINS: u64 = 256;
+
+/// HDR target the scene is rendered into, and the final pass tonemapping it onto the surface.
+/// Auto exposure needs compute shaders, without them `Exposure::Auto` falls back to an exposure of 0.
+#[derive(Debug)]
+pub struct Tonemapper {
+    pub hdr_texture: Texture,
+    pub uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub pipeline: wgpu::RenderPipeline,
+    /// settings for the textures cameras render into, which are always sRGB
+    pub offscreen_uniform_buffer: wgpu::Buffer,
+    offscreen_pipeline: wgpu::RenderPipeline,
+
+    pub exposure_buffer: wgpu::Buffer,
+    /// `array<atomic<u32>, 256>`, cleared again by the averaging pass
+    pub histogram_buffer: wgpu::Buffer,
+    /// the adapted scene luminance, carried over between frames
+    pub luminance_buffer: wgpu::Buffer,
+    histogram_bind_group_layout: Option<wgpu::BindGroupLayout>,
+    pub histogram_bind_group: Option<wgpu::BindGroup>,
+    histogram_pipeline: Option<wgpu::ComputePipeline>,
+    average_pipeline: Option<wgpu::ComputePipeline>,
+}
+
+impl Tonemapper {
+    pub fn new(bundle: &GraphicsBundle, compute_supported: bool) -> Self {
+        let device = bundle.device;
+        let hdr_texture = Texture::create_hdr_target(bundle, "hdr_texture");
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tonemapping Uniform Buffer"),
+            size: std::mem::size_of::<TonemappingUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let offscreen_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Tonemapping Uniform Buffer"),
+            size: std::mem::size_of::<TonemappingUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let exposure_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Exposure Uniform Buffer"),
+            size: std::mem::size_of::<ExposureParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let histogram_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Luminance Histogram Buffer"),
+            size: HISTOGRAM_BINS * std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        // starts out at the key value, an exposure of 0 until the first frame is measured
+        let luminance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Luminance Buffer"),
+            contents: bytemuck::cast_slice(&[KEY_VALUE]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                Tonemapper::hdr_texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+                Tonemapper::buffer_entry(1, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Uniform),
+                Tonemapper::buffer_entry(2, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Storage { read_only: true }),
+            ],
+            label: Some("tonemapping_bind_group_layout"),
+        });
+        let bind_group = Tonemapper::create_bind_group(device, &bind_group_layout, &hdr_texture, &uniform_buffer, &luminance_buffer);
+        let pipeline = Tonemapper::create_pipeline(device, &bind_group_layout, bundle.config.format);
+        let offscreen_pipeline = Tonemapper::create_pipeline(device, &bind_group_layout, Texture::RENDER_TEXTURE_FORMAT);
+
+        let mut tonemapper = Tonemapper {
+            hdr_texture,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+            offscreen_uniform_buffer,
+            offscreen_pipeline,
+            exposure_buffer,
+            histogram_buffer,
+            luminance_buffer,
+            histogram_bind_group_layout: None,
+            histogram_bind_group: None,
+            histogram_pipeline: None,
+            average_pipeline: None,
+        };
+        if compute_supported {
+            tonemapper.create_histogram_pipelines(device);
+        }
+        tonemapper
+    }
+
+    /// Recreates the HDR target at the surface's size.
+    pub fn resize(&mut self, bundle: &GraphicsBundle) {
+        self.hdr_texture = Texture::create_hdr_target(bundle, "hdr_texture");
+        self.bind_group = Tonemapper::create_bind_group(bundle.device, &self.bind_group_layout, &self.hdr_texture, &self.uniform_buffer, &self.luminance_buffer);
+        if let Some(layout) = &self.histogram_bind_group_layout {
+            self.histogram_bind_group = Some(self.create_histogram_bind_group(bundle.device, layout));
+        }
+    }
+
+    /// False when `Exposure::Auto` can't be used on this device.
+    pub fn supports_auto_exposure(&self) -> bool {
+        self.histogram_pipeline.is_some()
+    }
+
+    /// Uploads this frame's settings, `delta` is the time since the last frame in seconds.
+    pub fn write(&self, bundle: &GraphicsBundle, delta: f32) {
+        let settings: &GraphicsSettings = bundle.settings;
+        let auto_exposure = self.supports_auto_exposure() && matches!(settings.exposure, Exposure::Auto { .. });
+        let (exposure, key_value) = match settings.exposure {
+            Exposure::Manual(ev) => (ev.exp2(), KEY_VALUE),
+            Exposure::Auto { compensation, .. } if auto_exposure => (1.0, KEY_VALUE * compensation.exp2()),
+            Exposure::Auto { .. } => (1.0, KEY_VALUE),
+        };
+        let uniform = TonemappingUniform {
+            exposure,
+            curve: settings.tonemapping.to_raw(),
+            auto_exposure: auto_exposure as u32,
+            encode_srgb: !bundle.config.format.describe().srgb as u32,
+            key_value,
+            _padding: [0.0; 3],
+        };
+        bundle.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        let offscreen = TonemappingUniform { encode_srgb: 0, ..uniform };
+        bundle.queue.write_buffer(&self.offscreen_uniform_buffer, 0, bytemuck::cast_slice(&[offscreen]));
+
+        if let Exposure::Auto { min_ev, max_ev, speed, .. } = settings.exposure {
+            let range = (max_ev - min_ev).max(0.001);
+            let params = ExposureParams {
+                min_log_luminance: min_ev,
+                inverse_log_luminance_range: 1.0 / range,
+                log_luminance_range: range,
+                adaptation: (1.0 - (-delta * speed).exp()).clamp(0.0, 1.0),
+                pixel_count: bundle.config.width * bundle.config.height,
+                _padding: [0; 3],
+            };
+            bundle.queue.write_buffer(&self.exposure_buffer, 0, bytemuck::cast_slice(&[params]));
+        }
+    }
+
+    /// Measures the HDR target's average luminance, needs to run after the scene was rendered
+    /// and before `draw`. Does nothing without auto exposure.
+    pub fn measure_luminance(&self, encoder: &mut wgpu::CommandEncoder, settings: &GraphicsSettings, width: u32, height: u32) {
+        let (Some(histogram_pipeline), Some(average_pipeline), Some(bind_group)) = (&self.histogram_pipeline, &self.average_pipeline, &self.histogram_bind_group) else {
+            return;
+        };
+        if !matches!(settings.exposure, Exposure::Auto { .. }) {
+            return;
+        }
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Luminance Histogram Pass"),
+        });
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        compute_pass.set_pipeline(histogram_pipeline);
+        compute_pass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+        compute_pass.set_pipeline(average_pipeline);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    /// Tonemaps the HDR target onto `target`, which has the surface's size and format.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        Tonemapper::pass(encoder, target, &self.pipeline, &self.bind_group);
+    }
+
+    /// Bind group tonemapping another HDR texture into a `Texture::RENDER_TEXTURE_FORMAT` target,
+    /// with the exposure measured on the main HDR target.
+    pub fn offscreen_bind_group(&self, device: &wgpu::Device, hdr_texture: &Texture) -> wgpu::BindGroup {
+        Tonemapper::create_bind_group(device, &self.bind_group_layout, hdr_texture, &self.offscreen_uniform_buffer, &self.luminance_buffer)
+    }
+
+    /// Tonemaps the HDR texture of `bind_group`, from `offscreen_bind_group`, onto `target`.
+    pub fn draw_offscreen(&self, encoder: &mut wgpu::CommandEncoder, bind_group: &wgpu::BindGroup, target: &wgpu::TextureView) {
+        Tonemapper::pass(encoder, target, &self.offscreen_pipeline, bind_group);
+    }
+
+    fn pass(encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView, pipeline: &wgpu::RenderPipeline, bind_group: &wgpu::BindGroup) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tonemapping Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_pipeline(device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tonemapping Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/tonemap.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tonemapping Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tonemapping Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_histogram_pipelines(&mut self, device: &wgpu::Device) {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                Tonemapper::hdr_texture_entry(0, wgpu::ShaderStages::COMPUTE),
+                Tonemapper::buffer_entry(1, wgpu::ShaderStages::COMPUTE, wgpu::BufferBindingType::Uniform),
+                Tonemapper::buffer_entry(2, wgpu::ShaderStages::COMPUTE, wgpu::BufferBindingType::Storage { read_only: false }),
+                Tonemapper::buffer_entry(3, wgpu::ShaderStages::COMPUTE, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+            label: Some("luminance_histogram_bind_group_layout"),
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Luminance Histogram Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/luminance_histogram.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Luminance Histogram Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let create = |label, entry_point| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point,
+        });
+
+        self.histogram_pipeline = Some(create("Luminance Histogram Pipeline", "build_histogram"));
+        self.average_pipeline = Some(create("Average Luminance Pipeline", "average_luminance"));
+        self.histogram_bind_group = Some(self.create_histogram_bind_group(device, &layout));
+        self.histogram_bind_group_layout = Some(layout);
+    }
+
+    fn create_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, hdr_texture: &Texture, uniform_buffer: &wgpu::Buffer, luminance_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&hdr_texture.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: uniform_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: luminance_buffer.as_entire_binding() },
+            ],
+            label: Some("tonemapping_bind_group"),
+        })
+    }
+
+    fn create_histogram_bind_group(&self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&self.hdr_texture.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: self.exposure_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: self.histogram_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: self.luminance_buffer.as_entire_binding() },
+            ],
+            label: Some("luminance_histogram_bind_group"),
+        })
+    }
+
+    fn hdr_texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        }
+    }
+
+    fn buffer_entry(binding: u32, visibility: wgpu::ShaderStages, ty: wgpu::BufferBindingType) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+}
@@ -4,6 +4,8 @@ pub struct Vertex {
     pub position: [f32; 3],
     pub uvs: [f32; 2],
     pub normal: [f32; 3],
+    /// MikkTSpace tangent, `w` is the sign of the bitangent, `cross(normal, tangent.xyz) * w`
+    pub tangent: [f32; 4],
 }
 
 impl Vertex {
@@ -28,6 +30,11 @@ impl Vertex {
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x3,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -39,6 +46,7 @@ impl Default for Vertex {
             position: [0.0, 0.0, 0.0],
             uvs: [0.0, 0.0],
             normal: [0.0, 0.0, 0.0],
+            tangent: [1.0, 0.0, 0.0, 1.0],
         }
     }
 }
@@ -0,0 +1,78 @@
+use crate::{components::ClearFlags, graphics::{GraphicsBundle, Color, Texture}};
+
+/// Clears a camera's viewport inside a render pass shared with other cameras,
+/// a load op would clear the whole target.
+#[derive(Debug)]
+pub struct ViewportClear {
+    color_pipeline: wgpu::RenderPipeline,
+    depth_pipeline: wgpu::RenderPipeline,
+}
+
+impl ViewportClear {
+    pub fn new(bundle: &GraphicsBundle) -> Self {
+        let device = bundle.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Viewport Clear Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/viewport_clear.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Viewport Clear Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        // the output is replaced by the blend constant
+        let constant = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let create = |label, write_mask| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Texture::HDR_FORMAT,
+                    blend: Some(wgpu::BlendState { color: constant, alpha: constant }),
+                    write_mask,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: bundle.settings.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        ViewportClear {
+            color_pipeline: create("Viewport Clear Pipeline", wgpu::ColorWrites::ALL),
+            depth_pipeline: create("Viewport Depth Clear Pipeline", wgpu::ColorWrites::empty()),
+        }
+    }
+
+    /// Clears what `flags` asks for inside the current viewport and scissor rect.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, flags: ClearFlags, color: Color) {
+        let pipeline = match flags {
+            ClearFlags::ColorAndDepth => &self.color_pipeline,
+            ClearFlags::Depth => &self.depth_pipeline,
+            ClearFlags::Nothing => return,
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_blend_constant(color.to_wgpu());
+        render_pass.draw(0..3, 0..1);
+    }
+}
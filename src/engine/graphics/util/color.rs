@@ -1,5 +1,6 @@
+use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Color {
     r: f64,
     g: f64,
@@ -13,14 +14,24 @@ impl Color {
     pub const BLUE: Self = Color{ r: 0.0, g: 0.0, b: 1.0, a: 1.0};
     pub const BLACK: Self = Color{ r: 0.0, g: 0.0, b: 0.0, a: 1.0};
     pub const WHITE: Self = Color{ r: 1.0, g: 1.0, b: 1.0, a: 1.0};
-    pub fn new(r: f64, g: f64, b: f64, a: f64) -> Self {
+    pub const fn new(r: f64, g: f64, b: f64, a: f64) -> Self {
         Color { r, g, b, a }
     }
 
-    pub fn to_wgpu(&mut self) -> wgpu::Color {
+    pub fn to_wgpu(&self) -> wgpu::Color {
         wgpu::Color {r: self.r, g: self.g, b: self.b, a: self.a}
     }
 
+    pub fn lerp(&self, other: &Color, t: f32) -> Color {
+        let t = t as f64;
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
 }
 impl Into<[f32; 3]> for Color {
     fn into(self) -> [f32; 3] {
@@ -28,6 +39,12 @@ impl Into<[f32; 3]> for Color {
     }
 }
 
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [color.r as f32, color.g as f32, color.b as f32, color.a as f32]
+    }
+}
+
 impl Default for Color {
     fn default() -> Self {
         Color {
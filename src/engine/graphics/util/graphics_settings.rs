@@ -1,9 +1,32 @@
+use crate::graphics::{shadow::CascadeSplit, Tonemapping, Exposure, BloomSettings};
+
 
 pub struct GraphicsSettings {
     pub power_preference: wgpu::PowerPreference,
     pub alpha_mode:       wgpu::CompositeAlphaMode,
     pub present_mode:     wgpu::PresentMode,
     pub cull_back_face:   bool,
+    /// MSAA samples per pixel, 1 turns it off, lowered to what the adapter supports
+    pub sample_count:     u32,
+    /// width and height of each shadow map
+    pub shadow_map_size:  u32,
+    /// shadow maps available per frame, lights past this limit don't cast shadows
+    pub max_shadow_maps:  u32,
+    /// shadow maps per directional light, up to `shadow::MAX_CASCADES`
+    pub shadow_cascades:  u32,
+    pub cascade_split:    CascadeSplit,
+    /// how far from the camera directional lights cast shadows, capped by the camera's far plane
+    pub shadow_distance:  f32,
+    /// fraction of each cascade blended into the next one
+    pub cascade_blend:    f32,
+    /// upper limit for the anisotropic filtering of every texture sampler, 1 turns it off
+    pub max_anisotropy:   u8,
+    /// curve the HDR image is displayed with, can be changed between frames
+    pub tonemapping:      Tonemapping,
+    /// can be changed between frames
+    pub exposure:         Exposure,
+    /// can be changed between frames
+    pub bloom:            BloomSettings,
 }
 
 impl Default for GraphicsSettings {
@@ -13,6 +36,17 @@ impl Default for GraphicsSettings {
             alpha_mode:       wgpu::CompositeAlphaMode::Auto,
             present_mode:     wgpu::PresentMode::Fifo,
             cull_back_face:   true,
+            sample_count:     4,
+            shadow_map_size:  2048,
+            max_shadow_maps:  8,
+            shadow_cascades:  4,
+            cascade_split:    CascadeSplit::Practical(0.75),
+            shadow_distance:  100.0,
+            cascade_blend:    0.1,
+            max_anisotropy:   16,
+            tonemapping:      Tonemapping::Aces,
+            exposure:         Exposure::Manual(0.0),
+            bloom:            BloomSettings::default(),
         }
     }
 }
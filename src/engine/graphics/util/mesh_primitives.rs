@@ -61,10 +61,10 @@ impl MeshPrimitives {
     }
     pub fn plane (bundle: &GraphicsBundle, name: &str) -> Mesh {
         let vertices: &[Vertex] = &[
-            Vertex { position: [-0.5, 0.5, 0.0], uvs: [1.0, 0.0], normal: [0.0, 0.0, 1.0] },
-            Vertex { position: [0.5, 0.5, 0.0], uvs: [1.0, 1.0], normal: [0.0, 0.0, 1.0] },
-            Vertex { position: [0.5, -0.5, 0.0], uvs: [0.0, 1.0], normal: [0.0, 0.0, 1.0] },
-            Vertex { position: [-0.5, -0.5, 0.0], uvs: [0.0, 0.0], normal: [0.0, 0.0, 1.0] },
+            Vertex { position: [-0.5, 0.5, 0.0], uvs: [1.0, 0.0], normal: [0.0, 0.0, 1.0], ..Default::default() },
+            Vertex { position: [0.5, 0.5, 0.0], uvs: [1.0, 1.0], normal: [0.0, 0.0, 1.0], ..Default::default() },
+            Vertex { position: [0.5, -0.5, 0.0], uvs: [0.0, 1.0], normal: [0.0, 0.0, 1.0], ..Default::default() },
+            Vertex { position: [-0.5, -0.5, 0.0], uvs: [0.0, 0.0], normal: [0.0, 0.0, 1.0], ..Default::default() },
         ];
 
         let indices: &[u32] = &[
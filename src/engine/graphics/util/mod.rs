@@ -3,5 +3,6 @@ pub mod graphics_settings;
 pub mod graphics_bundle;
 pub mod color;
 pub mod mesh_primitives;
+pub mod tangents;
 
 pub use mesh_primitives::MeshPrimitives as MeshPrimitives;
\ No newline at end of file
@@ -27,7 +27,7 @@ pub fn create_render_pipeline(bundle: &GraphicsBundle, layout: &wgpu::PipelineLa
             module: &shader,
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState { // 4.
-                format: bundle.config.format,
+                format: Texture::HDR_FORMAT,
                 blend: Some(blend_state),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
@@ -47,13 +47,14 @@ pub fn create_render_pipeline(bundle: &GraphicsBundle, layout: &wgpu::PipelineLa
         },
         depth_stencil: Some(wgpu::DepthStencilState {
             format: Texture::DEPTH_FORMAT,
-            depth_write_enabled: true,
+            // blended surfaces would hide what's drawn behind them later
+            depth_write_enabled: !transparent,
             depth_compare: wgpu::CompareFunction::Less, // 1.
             stencil: wgpu::StencilState::default(), // 2.
             bias: wgpu::DepthBiasState::default(),
         }), // 1.
         multisample: wgpu::MultisampleState {
-            count: 1, // 2.
+            count: bundle.settings.sample_count, // 2.
             mask: !0, // 3.
             alpha_to_coverage_enabled: false, // 4.
         },
@@ -0,0 +1,61 @@
+use glam::Vec3;
+
+use crate::graphics::Vertex;
+
+/// Indexed triangle list as seen by mikktspace.
+struct TangentGeometry<'a> {
+    vertices: &'a mut [Vertex],
+    indices: &'a [u32],
+}
+
+impl<'a> TangentGeometry<'a> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.vertices[self.indices[face * 3 + vert] as usize]
+    }
+}
+
+impl<'a> bevy_mikktspace::Geometry for TangentGeometry<'a> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).uvs
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let index = self.indices[face * 3 + vert] as usize;
+        self.vertices[index].tangent = tangent;
+    }
+}
+
+/// Fills in `Vertex::tangent` with MikkTSpace tangents, the same ones baking tools use for normal maps.
+/// Vertices shared between faces keep the tangent of the last face written.
+///
+/// Returns `false` when the mesh has no usable normals or uvs, the tangents are left untouched then.
+pub fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) -> bool {
+    if indices.is_empty() || indices.len() % 3 != 0 {
+        return false;
+    }
+    // mikktspace can't cope with a mesh made only of degenerate triangles
+    let has_area = indices.chunks_exact(3).any(|triangle| {
+        let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(vertices[triangle[corner] as usize].position));
+        (b - a).cross(c - a).length_squared() > 0.0
+    });
+    if !has_area {
+        return false;
+    }
+    bevy_mikktspace::generate_tangents(&mut TangentGeometry { vertices, indices })
+}
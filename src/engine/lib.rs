@@ -30,6 +30,8 @@ pub mod graphics;
 pub mod components;
 pub mod systems;
 pub mod assets;
+pub mod scene;
+pub mod spatial;
 
 pub use assets::resources as resources;
 pub use input::Input as Input;
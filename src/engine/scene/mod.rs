@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use specs::{Entity, World, WorldExt, Join, Builder};
+
+use crate::{assets::{AssetManager, asset_manager::Error}, components::{AmbientLight, EnvironmentLight}};
+
+pub mod registry;
+pub use registry::SceneRegistry as SceneRegistry;
+pub use registry::SceneComponent as SceneComponent;
+pub use registry::SceneContext as SceneContext;
+
+pub mod prefab;
+pub use prefab::Prefab as Prefab;
+pub use prefab::PrefabOverrides as PrefabOverrides;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SceneFormat {
+    Ron,
+    Json,
+}
+
+impl SceneFormat {
+    /// Picks the format from a file extension, anything that isn't `.json` is read as RON.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => SceneFormat::Json,
+            _ => SceneFormat::Ron,
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, text: &str) -> Result<T, Error> {
+        match self {
+            SceneFormat::Ron => ron::from_str(text).map_err(|err| Error::InvalidFormat(err.to_string())),
+            SceneFormat::Json => serde_json::from_str(text).map_err(|err| Error::InvalidFormat(err.to_string())),
+        }
+    }
+}
+
+/// One saved entity, its components keyed by the name they were registered under.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneEntity {
+    pub components: BTreeMap<String, serde_json::Value>,
+}
+
+/// A text representation of a `World`'s entities.
+/// Only components registered in the `SceneRegistry` are saved.
+/// Asset references are saved by name, the assets have to be registered under the same names
+/// before the scene is instantiated.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+    #[serde(default)]
+    pub ambient_light: Option<AmbientLight>,
+    #[serde(default)]
+    pub environment_light: Option<EnvironmentLight>,
+}
+
+impl Scene {
+    pub fn from_world(world: &World, registry: &SceneRegistry) -> Result<Self, Error> {
+        let mut entities = Vec::new();
+        for entity in world.entities().join() {
+            let components = registry.save_entity(world, entity)?;
+            if components.is_empty() {
+                continue;
+            }
+            entities.push(SceneEntity { components: components.into_iter().collect() });
+        }
+        let ambient_light = world.try_fetch::<AmbientLight>().map(|ambient| *ambient);
+        let environment_light = world.try_fetch::<EnvironmentLight>().map(|environment| (*environment).clone());
+        Ok(Scene { entities, ambient_light, environment_light })
+    }
+
+    pub fn parse(text: &str, format: SceneFormat) -> Result<Self, Error> {
+        format.parse(text)
+    }
+
+    pub fn to_text(&self, format: SceneFormat) -> Result<String, Error> {
+        match format {
+            SceneFormat::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|err| Error::InvalidFormat(err.to_string())),
+            SceneFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|err| Error::InvalidFormat(err.to_string())),
+        }
+    }
+
+    /// Loads a scene from the asset folder, the format is picked from the extension.
+    pub async fn load(file_name: &str) -> Result<Self, Error> {
+        let text = AssetManager::load_string(file_name).await.map_err(read_error)?;
+        Scene::parse(&text, SceneFormat::from_path(file_name))
+    }
+
+    /// Reads a scene from anywhere on disk, like one written by `save`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(read_error)?;
+        Scene::parse(&text, SceneFormat::from_path(&path.to_string_lossy()))
+    }
+
+    /// Writes the scene to `path`, the format is picked from the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let text = self.to_text(SceneFormat::from_path(&path.to_string_lossy()))?;
+        fs::write(path, text).map_err(|_| Error::FileWriteFailed)
+    }
+
+    /// Creates every entity in the scene, returns them in file order.
+    /// If a component fails to load the entities created so far are deleted.
+    pub fn instantiate(&self, world: &mut World, registry: &SceneRegistry, context: &SceneContext) -> Result<Vec<Entity>, Error> {
+        if let Some(ambient_light) = self.ambient_light {
+            world.insert(ambient_light);
+        }
+        if let Some(environment_light) = &self.environment_light {
+            world.insert(environment_light.clone());
+        }
+        let mut created = Vec::new();
+        for scene_entity in self.entities.iter() {
+            match Scene::instantiate_entity(scene_entity, world, registry, context) {
+                Ok(entity) => created.push(entity),
+                Err(err) => {
+                    world.delete_entities(&created).ok();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(created)
+    }
+
+    pub(crate) fn instantiate_entity(scene_entity: &SceneEntity, world: &mut World, registry: &SceneRegistry, context: &SceneContext) -> Result<Entity, Error> {
+        let entity = world.create_entity().build();
+        for (name, value) in scene_entity.components.iter() {
+            if let Err(err) = registry.load_component(world, entity, name, value.clone(), context) {
+                world.delete_entity(entity).ok();
+                return Err(err);
+            }
+        }
+        Ok(entity)
+    }
+}
+
+fn read_error(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::NotFound => Error::FileNotFound,
+        _ => Error::FileReadFailed(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use crate::{components::{Transform, Sprite, Light, Camera, Name, Tags}, graphics::Color};
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<Sprite>();
+        world.register::<Light>();
+        world.register::<Camera>();
+        world.register::<Name>();
+        world.register::<Tags>();
+
+        let mut transform = Transform::new(1.0, -2.5, 3.0);
+        transform.rotation = Quat::from_rotation_y(0.5);
+        transform.scale = Vec3::new(2.0, 1.0, 0.5);
+        world.create_entity().with(transform).with(Name::new("crate")).with(Tags::new(&["prop", "physics"])).build();
+        world.create_entity().with(Name::new("marker")).build();
+        // nothing registered to save, left out of the scene
+        world.create_entity().build();
+        world.insert(AmbientLight::new(Color::new(0.25, 0.5, 1.0, 1.0), 0.3));
+        world
+    }
+
+    fn round_trip(format: SceneFormat) {
+        let scene = Scene::from_world(&world(), &SceneRegistry::new()).unwrap();
+        assert_eq!(scene.entities.len(), 2);
+
+        let text = scene.to_text(format).unwrap();
+        let parsed = Scene::parse(&text, format).unwrap();
+        let names = |scene: &Scene| scene.entities.iter().map(|entity| entity.components.keys().cloned().collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(names(&parsed), names(&scene));
+        assert_eq!(parsed.ambient_light.map(|ambient| ambient.radiance()), Some([0.075, 0.15, 0.3]));
+        assert!(parsed.environment_light.is_none());
+
+        // compared as the components, JSON may read a float back as a neighbouring f64 that is still the same f32
+        let component = |entity: usize, name: &str| parsed.entities[entity].components[name].clone();
+        let transform: Transform = serde_json::from_value(component(0, "Transform")).unwrap();
+        assert_eq!(transform.position, Vec3::new(1.0, -2.5, 3.0));
+        assert_eq!(transform.rotation, Quat::from_rotation_y(0.5));
+        assert_eq!(transform.scale, Vec3::new(2.0, 1.0, 0.5));
+        assert_eq!(serde_json::from_value::<Name>(component(0, "Name")).unwrap(), Name::new("crate"));
+        assert_eq!(serde_json::from_value::<Tags>(component(0, "Tags")).unwrap(), Tags::new(&["prop", "physics"]));
+        assert_eq!(serde_json::from_value::<Name>(component(1, "Name")).unwrap(), Name::new("marker"));
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        round_trip(SceneFormat::Ron);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip(SceneFormat::Json);
+    }
+
+    #[test]
+    fn saved_files_read_back() {
+        let scene = Scene::from_world(&world(), &SceneRegistry::new()).unwrap();
+        let path = std::env::temp_dir().join(format!("wvreng_scene_{}.json", std::process::id()));
+        scene.save(&path).unwrap();
+        let read = Scene::read(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap().entities.len(), scene.entities.len());
+        assert!(matches!(Scene::read(&path), Err(Error::FileNotFound)));
+    }
+
+    #[test]
+    fn lights_are_optional() {
+        let scene = Scene::parse("(entities: [])", SceneFormat::Ron).unwrap();
+        assert!(scene.entities.is_empty() && scene.ambient_light.is_none() && scene.environment_light.is_none());
+        let scene = Scene::parse("{\"entities\": []}", SceneFormat::Json).unwrap();
+        assert!(scene.entities.is_empty() && scene.ambient_light.is_none());
+    }
+
+    #[test]
+    fn invalid_text_is_an_error() {
+        assert!(matches!(Scene::parse("(entities: [", SceneFormat::Ron), Err(Error::InvalidFormat(_))));
+        assert!(matches!(Scene::parse("{\"entities\": 1}", SceneFormat::Json), Err(Error::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn picks_the_format_from_the_extension() {
+        assert_eq!(SceneFormat::from_path("levels/first.json"), SceneFormat::Json);
+        assert_eq!(SceneFormat::from_path("levels/first.ron"), SceneFormat::Ron);
+        assert_eq!(SceneFormat::from_path("levels/first"), SceneFormat::Ron);
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+use specs::{Entity, World, WorldExt};
+
+use crate::{
+    assets::asset_manager::Error,
+    components::{Transform, Parent},
+    scene::{Scene, SceneEntity, SceneFormat, SceneRegistry, SceneContext},
+};
+
+/// An entity template, children are spawned as separate entities with a `Parent`.
+/// Child transforms are relative to the root.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrefabNode {
+    #[serde(default)]
+    pub components: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub children: Vec<PrefabNode>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Prefab {
+    pub root: PrefabNode,
+}
+
+/// Field values replacing the prefab's defaults on the root entity when spawning.
+/// Components missing from the prefab are added.
+#[derive(Debug, Clone, Default)]
+pub struct PrefabOverrides {
+    pub components: BTreeMap<String, serde_json::Value>,
+}
+
+impl PrefabOverrides {
+    pub fn new() -> Self {
+        PrefabOverrides { components: BTreeMap::new() }
+    }
+
+    /// Overrides a single field, e.g. `set("Light", "intensity", 2.0)`.
+    pub fn set<T: Serialize>(mut self, component: &str, field: &str, value: T) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(serde_json::Value::Null);
+        let entry = self.components.entry(String::from(component))
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+        if let serde_json::Value::Object(map) = entry {
+            map.insert(String::from(field), value);
+        }
+        self
+    }
+
+    /// Replaces or adds a whole component.
+    pub fn with_component<T: Serialize>(mut self, component: &str, value: T) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(serde_json::Value::Null);
+        self.components.insert(String::from(component), value);
+        self
+    }
+}
+
+impl Prefab {
+    pub fn parse(text: &str, format: SceneFormat) -> Result<Self, Error> {
+        format.parse(text)
+    }
+
+    /// Spawns the prefab with its root Transform placed relative to `transform`, returns the root entity.
+    pub fn spawn(&self, world: &mut World, registry: &SceneRegistry, context: &SceneContext, transform: Transform, overrides: Option<&PrefabOverrides>) -> Result<Entity, Error> {
+        let mut root = self.root.clone();
+        if let Some(overrides) = overrides {
+            for (name, value) in overrides.components.iter() {
+                match root.components.get_mut(name) {
+                    Some(existing) => merge_values(existing, value),
+                    None => { root.components.insert(name.clone(), value.clone()); },
+                }
+            }
+        }
+
+        let mut created = Vec::new();
+        let transform = transform.mul_transform(&local_transform(&root)?);
+        match spawn_node(&root, None, transform, world, registry, context, &mut created) {
+            Ok(entity) => Ok(entity),
+            Err(err) => {
+                world.delete_entities(&created).ok();
+                Err(err)
+            }
+        }
+    }
+}
+
+fn spawn_node(node: &PrefabNode, parent: Option<Entity>, transform: Transform, world: &mut World, registry: &SceneRegistry, context: &SceneContext, created: &mut Vec<Entity>) -> Result<Entity, Error> {
+    let mut scene_entity = SceneEntity { components: node.components.clone() };
+    scene_entity.components.insert(String::from("Transform"), to_value(&transform)?);
+
+    let entity = Scene::instantiate_entity(&scene_entity, world, registry, context)?;
+    created.push(entity);
+    if let Some(parent) = parent {
+        world.register::<Parent>();
+        world.write_storage::<Parent>().insert(entity, Parent(parent)).map_err(|_| Error::NullReference)?;
+    }
+
+    for child in node.children.iter() {
+        let local = local_transform(child)?;
+        spawn_node(child, Some(entity), transform.mul_transform(&local), world, registry, context, created)?;
+    }
+    Ok(entity)
+}
+
+/// The authored Transform of a node, relative to its parent.
+fn local_transform(node: &PrefabNode) -> Result<Transform, Error> {
+    match node.components.get("Transform") {
+        Some(value) => serde_json::from_value(value.clone()).map_err(|err| Error::InvalidFormat(err.to_string())),
+        None => Ok(Transform::new(0.0, 0.0, 0.0)),
+    }
+}
+
+fn to_value(transform: &Transform) -> Result<serde_json::Value, Error> {
+    serde_json::to_value(transform).map_err(|err| Error::InvalidFormat(err.to_string()))
+}
+
+/// Recursively writes the fields of `value` over `target`.
+fn merge_values(target: &mut serde_json::Value, value: &serde_json::Value) {
+    match (target, value) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(value)) => {
+            for (key, value) in value.iter() {
+                match target.get_mut(key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => { target.insert(key.clone(), value.clone()); },
+                }
+            }
+        },
+        (target, value) => *target = value.clone(),
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Serialize, de::DeserializeOwned};
+use specs::{Component, Entity, World, WorldExt, storage::MaskedStorage};
+
+use crate::{
+    assets::{AssetManager, asset_manager::Error},
+    components::{Transform, Sprite, Light, Camera, Name, Tags},
+    graphics::GraphicsBundle,
+};
+
+/// Everything a component may need to rebuild itself from a scene file,
+/// e.g. GPU buffers for cameras, or asset lookups for sprites.
+pub struct SceneContext<'a> {
+    pub bundle: &'a GraphicsBundle<'a>,
+    pub asset_manager: &'a AssetManager,
+}
+
+/// Components that need more than a plain serde derive to be saved,
+/// usually because they own GPU resources.
+pub trait SceneComponent: Component + Sized {
+    type Data: Serialize + DeserializeOwned;
+
+    fn to_scene(&self) -> Self::Data;
+    fn from_scene(data: Self::Data, context: &SceneContext) -> Result<Self, Error>;
+}
+
+type SaveFn = fn(&World, Entity) -> Option<Result<serde_json::Value, Error>>;
+type LoadFn = fn(&mut World, Entity, serde_json::Value, &SceneContext) -> Result<(), Error>;
+
+struct RegisteredComponent {
+    name: String,
+    save: SaveFn,
+    load: LoadFn,
+}
+
+/// Maps component names used in scene files to the code that saves and loads them.
+pub struct SceneRegistry {
+    components: Vec<RegisteredComponent>,
+}
+
+impl SceneRegistry {
+    /// Registry with every engine component already registered.
+    pub fn new() -> Self {
+        let mut registry = SceneRegistry::empty();
+        registry.register_serde::<Transform>("Transform");
+        registry.register::<Sprite>("Sprite");
+        registry.register::<Light>("Light");
+        registry.register::<Camera>("Camera");
+        registry.register_serde::<Name>("Name");
+        registry.register_serde::<Tags>("Tags");
+        registry
+    }
+
+    pub fn empty() -> Self {
+        SceneRegistry { components: Vec::new() }
+    }
+
+    /// Registers a component implementing `SceneComponent`.
+    pub fn register<T>(&mut self, name: &str) where T: SceneComponent + Send + Sync, T::Storage: Default {
+        self.insert(name, save_scene_component::<T>, load_scene_component::<T>);
+    }
+
+    /// Registers a component that can be (de)serialized as is.
+    pub fn register_serde<T>(&mut self, name: &str) where T: Component + Serialize + DeserializeOwned + Send + Sync, T::Storage: Default {
+        self.insert(name, save_serde_component::<T>, load_serde_component::<T>);
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.components.iter().any(|component| component.name == name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.components.iter().map(|component| component.name.as_str())
+    }
+
+    fn insert(&mut self, name: &str, save: SaveFn, load: LoadFn) {
+        self.components.retain(|component| component.name != name);
+        self.components.push(RegisteredComponent { name: String::from(name), save, load });
+    }
+
+    /// Serializes every registered component found on `entity`, keyed by component name.
+    pub fn save_entity(&self, world: &World, entity: Entity) -> Result<Vec<(String, serde_json::Value)>, Error> {
+        let mut components = Vec::new();
+        for component in self.components.iter() {
+            if let Some(value) = (component.save)(world, entity) {
+                components.push((component.name.clone(), value?));
+            }
+        }
+        Ok(components)
+    }
+
+    pub fn load_component(&self, world: &mut World, entity: Entity, name: &str, value: serde_json::Value, context: &SceneContext) -> Result<(), Error> {
+        match self.components.iter().find(|component| component.name == name) {
+            Some(component) => (component.load)(world, entity, value, context),
+            None => Err(Error::UnknownComponent(String::from(name))),
+        }
+    }
+}
+
+impl Default for SceneRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn to_value<T: Serialize>(data: &T) -> Result<serde_json::Value, Error> {
+    serde_json::to_value(data).map_err(|err| Error::InvalidFormat(err.to_string()))
+}
+
+fn from_value<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, Error> {
+    serde_json::from_value(value).map_err(|err| Error::InvalidFormat(err.to_string()))
+}
+
+fn save_scene_component<T: SceneComponent>(world: &World, entity: Entity) -> Option<Result<serde_json::Value, Error>> {
+    if !world.has_value::<MaskedStorage<T>>() {
+        return None;
+    }
+    world.read_storage::<T>().get(entity).map(|component| to_value(&component.to_scene()))
+}
+
+fn load_scene_component<T: SceneComponent + Send + Sync>(world: &mut World, entity: Entity, value: serde_json::Value, context: &SceneContext) -> Result<(), Error> where T::Storage: Default {
+    let component = T::from_scene(from_value(value)?, context)?;
+    world.register::<T>();
+    world.write_storage::<T>().insert(entity, component).map_err(|_| Error::NullReference)?;
+    Ok(())
+}
+
+fn save_serde_component<T: Component + Serialize>(world: &World, entity: Entity) -> Option<Result<serde_json::Value, Error>> {
+    if !world.has_value::<MaskedStorage<T>>() {
+        return None;
+    }
+    world.read_storage::<T>().get(entity).map(to_value)
+}
+
+fn load_serde_component<T: Component + DeserializeOwned + Send + Sync>(world: &mut World, entity: Entity, value: serde_json::Value, context: &SceneContext) -> Result<(), Error> where T::Storage: Default {
+    let component: T = from_value(value)?;
+    world.register::<T>();
+    world.write_storage::<T>().insert(entity, component).map_err(|_| Error::NullReference)?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use glam::{Mat4, Vec3};
+
+/// Axis aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Inverted box that grows to fit the first point added to it.
+    pub const EMPTY: Self = Aabb { min: Vec3::splat(f32::MAX), max: Vec3::splat(f32::MIN) };
+
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Self {
+        let mut aabb = Aabb::EMPTY;
+        for point in points {
+            aabb.grow(point);
+        }
+        aabb
+    }
+
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Self {
+        Aabb { min: center - half_extents, max: center + half_extents }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn grow(&mut self, point: Vec3) {
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.min.cmple(point).all() && self.max.cmpge(point).all()
+    }
+
+    /// Squared distance from `point` to the closest point of the box, 0 when inside.
+    pub fn distance_squared(&self, point: Vec3) -> f32 {
+        let closest = point.clamp(self.min, self.max);
+        closest.distance_squared(point)
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.distance_squared(center) <= radius * radius
+    }
+
+    /// Bounds of this box after being transformed by `matrix`.
+    pub fn transform(&self, matrix: &Mat4) -> Aabb {
+        // Arvo's method, transform the center and the extents on each axis separately
+        let center = matrix.transform_point3(self.center());
+        let extents = self.half_extents();
+        let new_extents = Vec3::new(
+            matrix.x_axis.x.abs() * extents.x + matrix.y_axis.x.abs() * extents.y + matrix.z_axis.x.abs() * extents.z,
+            matrix.x_axis.y.abs() * extents.x + matrix.y_axis.y.abs() * extents.y + matrix.z_axis.y.abs() * extents.z,
+            matrix.x_axis.z.abs() * extents.x + matrix.y_axis.z.abs() * extents.y + matrix.z_axis.z.abs() * extents.z,
+        );
+        Aabb::from_center(center, new_extents)
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::EMPTY
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray { origin, direction: direction.normalize() }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Distance along the ray to where it enters `aabb`, 0 if it starts inside.
+    pub fn intersect_aabb(&self, aabb: &Aabb, max_distance: f32) -> Option<f32> {
+        let inverse = self.direction.recip();
+        let t1 = (aabb.min - self.origin) * inverse;
+        let t2 = (aabb.max - self.origin) * inverse;
+        let t_near = t1.min(t2).max_element().max(0.0);
+        let t_far = t1.max(t2).min_element().min(max_distance);
+        if t_near <= t_far {
+            Some(t_near)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use glam::{Mat4, Vec3, Vec4};
+
+use super::Aabb;
+
+/// Plane in the form `normal . point + d = 0`, the normal points inside the frustum.
+#[derive(Debug, Copy, Clone)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub d: f32,
+}
+
+impl Plane {
+    fn from_vec4(v: Vec4) -> Self {
+        let length = v.truncate().length();
+        Plane { normal: v.truncate() / length, d: v.w / length }
+    }
+
+    pub fn distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) + self.d
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+    /// left, right, bottom, top, near, far
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a wgpu style (0..1 depth) view projection matrix.
+    pub fn from_view_proj(view_proj: &Mat4) -> Self {
+        let (r0, r1, r2, r3) = (view_proj.row(0), view_proj.row(1), view_proj.row(2), view_proj.row(3));
+        Frustum {
+            planes: [
+                Plane::from_vec4(r3 + r0),
+                Plane::from_vec4(r3 - r0),
+                Plane::from_vec4(r3 + r1),
+                Plane::from_vec4(r3 - r1),
+                Plane::from_vec4(r2),
+                Plane::from_vec4(r3 - r2),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.distance(point) >= 0.0)
+    }
+
+    /// Conservative test, boxes near the corners of the frustum may pass while outside.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        for plane in self.planes.iter() {
+            // the corner furthest along the plane normal
+            let positive = Vec3::select(plane.normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            if plane.distance(positive) < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.distance(center) >= -radius)
+    }
+}
@@ -0,0 +1,10 @@
+pub mod aabb;
+pub use aabb::Aabb as Aabb;
+pub use aabb::Ray as Ray;
+
+pub mod frustum;
+pub use frustum::Frustum as Frustum;
+pub use frustum::Plane as Plane;
+
+pub mod spatial_index;
+pub use spatial_index::SpatialIndex as SpatialIndex;
@@ -0,0 +1,598 @@
+use std::{collections::{HashMap, HashSet, BinaryHeap}, cmp::Ordering, mem};
+
+use glam::Vec3;
+use specs::{Entity, World, WorldExt, Join, ReaderId, storage::ComponentEvent};
+
+use crate::{
+    assets::AssetManager,
+    components::{Transform, Sprite},
+};
+
+use super::{Aabb, Ray, Frustum};
+
+const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    /// ids of the entities held by the leaf
+    Leaf(Vec<u32>),
+    /// indices of the two children
+    Branch(usize, usize),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: Aabb,
+    parent: Option<usize>,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Item {
+    entity: Entity,
+    bounds: Aabb,
+    leaf: usize,
+}
+
+/// Bounding volume hierarchy over the world space bounds of every entity with a
+/// `Transform` and `Sprite`, used for culling and gameplay proximity queries.
+///
+/// `sync` reads the `Transform` and `Sprite` change events once per frame and only
+/// touches the entities that changed, moved entities refit the nodes above them
+/// instead of rebuilding the tree. `rebuild` rebalances it from scratch.
+#[derive(Debug, Default)]
+pub struct SpatialIndex {
+    /// indexed by entity id, like the component change events
+    items: HashMap<u32, Item>,
+    nodes: Vec<Node>,
+    transform_reader: Option<ReaderId<ComponentEvent>>,
+    sprite_reader: Option<ReaderId<ComponentEvent>>,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        SpatialIndex::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get_bounds(&self, entity: Entity) -> Option<&Aabb> {
+        self.items.get(&entity.id()).filter(|item| item.entity == entity).map(|item| &item.bounds)
+    }
+
+    /// Bounds of every indexed entity.
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |root| root.bounds)
+    }
+
+    /// Moves an entity and refits the nodes above it, or inserts it into the leaf that grows the least.
+    pub fn update(&mut self, entity: Entity, bounds: Aabb) {
+        if let Some(item) = self.items.get_mut(&entity.id()) {
+            // the id may have been reused by a newer entity
+            item.entity = entity;
+            if item.bounds != bounds {
+                item.bounds = bounds;
+                let leaf = item.leaf;
+                self.refit(leaf);
+            }
+            return;
+        }
+
+        if self.nodes.is_empty() {
+            self.nodes.push(Node { bounds: Aabb::EMPTY, parent: None, kind: NodeKind::Leaf(Vec::new()) });
+        }
+        let leaf = self.choose_leaf(&bounds);
+        self.items.insert(entity.id(), Item { entity, bounds, leaf });
+        let NodeKind::Leaf(ids) = &mut self.nodes[leaf].kind else {
+            unreachable!("SpatialIndex : entities can only be inserted into leaves");
+        };
+        ids.push(entity.id());
+        let full = ids.len() > MAX_LEAF_SIZE;
+        self.refit(leaf);
+        if full {
+            let NodeKind::Leaf(ids) = &mut self.nodes[leaf].kind else { unreachable!() };
+            let ids = mem::take(ids);
+            self.build_node(leaf, ids);
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if self.items.get(&entity.id()).is_some_and(|item| item.entity == entity) {
+            self.remove_id(entity.id());
+        }
+    }
+
+    fn remove_id(&mut self, id: u32) {
+        let Some(item) = self.items.remove(&id) else {
+            return;
+        };
+        if let NodeKind::Leaf(ids) = &mut self.nodes[item.leaf].kind {
+            ids.retain(|other| *other != id);
+        }
+        self.refit(item.leaf);
+    }
+
+    /// Applies the `Transform` and `Sprite` changes made since the last call.
+    ///
+    /// The first call indexes every entity, later ones only the entities whose components were
+    /// inserted, modified or removed. Joining `WriteStorage<Transform>` mutably flags every joined
+    /// transform, even the ones left untouched.
+    pub fn sync(&mut self, world: &World, asset_manager: &AssetManager) {
+        self.sync_with(world, |sprite| asset_manager.get_mesh(sprite.mesh.clone()).ok().map(|mesh| mesh.bounds));
+    }
+
+    fn sync_with<F>(&mut self, world: &World, mesh_bounds: F)
+    where F: Fn(&Sprite) -> Option<Aabb> {
+        let (Some(transform_reader), Some(sprite_reader)) = (&mut self.transform_reader, &mut self.sprite_reader) else {
+            self.transform_reader = Some(world.write_storage::<Transform>().register_reader());
+            self.sprite_reader = Some(world.write_storage::<Sprite>().register_reader());
+
+            let entities = world.entities();
+            let transforms = world.read_storage::<Transform>();
+            let sprites = world.read_storage::<Sprite>();
+            self.items.clear();
+            for (entity, transform, sprite) in (&entities, &transforms, &sprites).join() {
+                if let Some(bounds) = mesh_bounds(sprite) {
+                    let bounds = bounds.transform(&transform.matrix());
+                    self.items.insert(entity.id(), Item { entity, bounds, leaf: 0 });
+                }
+            }
+            self.rebuild();
+            return;
+        };
+
+        let entities = world.entities();
+        let transforms = world.read_storage::<Transform>();
+        let sprites = world.read_storage::<Sprite>();
+
+        let mut changed = HashSet::new();
+        for event in transforms.channel().read(transform_reader).chain(sprites.channel().read(sprite_reader)) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) | ComponentEvent::Removed(id) => changed.insert(*id),
+            };
+        }
+
+        for id in changed {
+            let entity = entities.entity(id);
+            let bounds = match (transforms.get(entity), sprites.get(entity)) {
+                (Some(transform), Some(sprite)) if entities.is_alive(entity) => {
+                    mesh_bounds(sprite).map(|bounds| bounds.transform(&transform.matrix()))
+                },
+                _ => None,
+            };
+            match bounds {
+                Some(bounds) => self.update(entity, bounds),
+                None => self.remove_id(id),
+            }
+        }
+    }
+
+    /// Rebuilds the tree from scratch by splitting at the median, leaving it balanced.
+    pub fn rebuild(&mut self) {
+        self.nodes.clear();
+        if self.items.is_empty() {
+            return;
+        }
+        let ids = self.items.keys().copied().collect();
+        self.nodes.push(Node { bounds: Aabb::EMPTY, parent: None, kind: NodeKind::Leaf(Vec::new()) });
+        self.build_node(0, ids);
+    }
+
+    fn build_node(&mut self, node: usize, mut ids: Vec<u32>) {
+        let mut bounds = Aabb::EMPTY;
+        let mut centers = Aabb::EMPTY;
+        for id in &ids {
+            bounds = bounds.union(&self.items[id].bounds);
+            centers.grow(self.items[id].bounds.center());
+        }
+        self.nodes[node].bounds = bounds;
+
+        if ids.len() <= MAX_LEAF_SIZE {
+            for id in &ids {
+                if let Some(item) = self.items.get_mut(id) {
+                    item.leaf = node;
+                }
+            }
+            self.nodes[node].kind = NodeKind::Leaf(ids);
+            return;
+        }
+
+        // split at the median along the axis the centers are most spread out on
+        let spread = centers.max - centers.min;
+        let axis = if spread.x >= spread.y && spread.x >= spread.z { 0 } else if spread.y >= spread.z { 1 } else { 2 };
+        let middle = ids.len() / 2;
+        let items = &self.items;
+        ids.select_nth_unstable_by(middle, |a, b| {
+            items[a].bounds.center()[axis].partial_cmp(&items[b].bounds.center()[axis]).unwrap_or(Ordering::Equal)
+        });
+        let right_ids = ids.split_off(middle);
+
+        let left = self.nodes.len();
+        self.nodes.push(Node { bounds: Aabb::EMPTY, parent: Some(node), kind: NodeKind::Leaf(Vec::new()) });
+        self.nodes.push(Node { bounds: Aabb::EMPTY, parent: Some(node), kind: NodeKind::Leaf(Vec::new()) });
+        self.nodes[node].kind = NodeKind::Branch(left, left + 1);
+        self.build_node(left, ids);
+        self.build_node(left + 1, right_ids);
+    }
+
+    /// Descends towards the child whose surface area grows the least.
+    fn choose_leaf(&self, bounds: &Aabb) -> usize {
+        let growth = |node: usize| {
+            let current = &self.nodes[node].bounds;
+            surface_area(&current.union(bounds)) - surface_area(current)
+        };
+        let mut node = 0;
+        while let NodeKind::Branch(left, right) = self.nodes[node].kind {
+            node = if growth(left) <= growth(right) { left } else { right };
+        }
+        node
+    }
+
+    /// Recomputes the bounds of `node` and its ancestors, stopping at the first one that didn't change.
+    fn refit(&mut self, node: usize) {
+        let mut current = Some(node);
+        while let Some(node) = current {
+            let bounds = match &self.nodes[node].kind {
+                NodeKind::Leaf(ids) => ids.iter().fold(Aabb::EMPTY, |bounds, id| bounds.union(&self.items[id].bounds)),
+                NodeKind::Branch(left, right) => self.nodes[*left].bounds.union(&self.nodes[*right].bounds),
+            };
+            if bounds == self.nodes[node].bounds {
+                break;
+            }
+            self.nodes[node].bounds = bounds;
+            current = self.nodes[node].parent;
+        }
+    }
+
+    /// Visits every leaf entity whose node passes `node_test`, then filters by `entity_test`.
+    fn traverse<N, E>(&self, node_test: N, mut entity_test: E) -> Vec<Entity>
+    where N: Fn(&Aabb) -> bool, E: FnMut(&Aabb) -> bool {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            // leaves emptied by removals stay in the tree until the next rebuild
+            if node.bounds.is_empty() || !node_test(&node.bounds) {
+                continue;
+            }
+            match &node.kind {
+                NodeKind::Leaf(ids) => {
+                    for id in ids {
+                        let item = &self.items[id];
+                        if entity_test(&item.bounds) {
+                            found.push(item.entity);
+                        }
+                    }
+                },
+                NodeKind::Branch(left, right) => {
+                    stack.push(*left);
+                    stack.push(*right);
+                },
+            }
+        }
+        found
+    }
+
+    /// Entities whose bounds overlap `aabb`.
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<Entity> {
+        self.traverse(|bounds| bounds.intersects(aabb), |bounds| bounds.intersects(aabb))
+    }
+
+    /// Entities whose bounds are at most `radius` away from `center`.
+    pub fn query_sphere(&self, center: Vec3, radius: f32) -> Vec<Entity> {
+        self.traverse(|bounds| bounds.intersects_sphere(center, radius), |bounds| bounds.intersects_sphere(center, radius))
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<Entity> {
+        self.traverse(|bounds| frustum.intersects_aabb(bounds), |bounds| frustum.intersects_aabb(bounds))
+    }
+
+    /// Every entity hit by the ray within `max_distance`, closest first.
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Vec<(Entity, f32)> {
+        let entities = self.traverse(
+            |bounds| ray.intersect_aabb(bounds, max_distance).is_some(),
+            |bounds| ray.intersect_aabb(bounds, max_distance).is_some(),
+        );
+        let mut hits: Vec<(Entity, f32)> = entities.into_iter()
+            .filter_map(|entity| ray.intersect_aabb(&self.items[&entity.id()].bounds, max_distance).map(|distance| (entity, distance)))
+            .collect();
+        hits.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        hits
+    }
+
+    pub fn raycast_first(&self, ray: &Ray, max_distance: f32) -> Option<(Entity, f32)> {
+        self.raycast(ray, max_distance).into_iter().next()
+    }
+
+    /// The `k` entities closest to `point` by distance to their bounds, closest first.
+    pub fn nearest(&self, point: Vec3, k: usize) -> Vec<(Entity, f32)> {
+        let mut found: Vec<(Entity, f32)> = Vec::new();
+        if self.nodes.is_empty() || self.nodes[0].bounds.is_empty() || k == 0 {
+            return found;
+        }
+        // best first search, nodes are visited in order of distance to their bounds
+        let mut queue = BinaryHeap::new();
+        queue.push(Candidate { distance: self.nodes[0].bounds.distance_squared(point), index: 0, entity: None });
+        while let Some(candidate) = queue.pop() {
+            if found.len() == k {
+                break;
+            }
+            if let Some(entity) = candidate.entity {
+                found.push((entity, candidate.distance.sqrt()));
+                continue;
+            }
+            match &self.nodes[candidate.index].kind {
+                NodeKind::Leaf(ids) => {
+                    for id in ids {
+                        let item = &self.items[id];
+                        queue.push(Candidate { distance: item.bounds.distance_squared(point), index: 0, entity: Some(item.entity) });
+                    }
+                },
+                NodeKind::Branch(left, right) => {
+                    for child in [*left, *right] {
+                        let bounds = &self.nodes[child].bounds;
+                        if !bounds.is_empty() {
+                            queue.push(Candidate { distance: bounds.distance_squared(point), index: child, entity: None });
+                        }
+                    }
+                },
+            }
+        }
+        found
+    }
+}
+
+fn surface_area(aabb: &Aabb) -> f32 {
+    if aabb.is_empty() {
+        return 0.0;
+    }
+    let size = aabb.max - aabb.min;
+    2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+}
+
+struct Candidate {
+    distance: f32,
+    index: usize,
+    entity: Option<Entity>,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance
+    }
+}
+impl Eq for Candidate {}
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Candidate {
+    // reversed so the BinaryHeap pops the closest candidate first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.partial_cmp(&self.distance).unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use glam::Mat4;
+    use specs::Builder;
+
+    use crate::components::Camera;
+
+    use super::*;
+
+    /// A 10 by 10 grid of unit cubes on the XZ plane, two units apart, with enough entities to split the tree.
+    fn grid(world: &mut World, index: &mut SpatialIndex) -> Vec<(Entity, Aabb)> {
+        let mut cubes = Vec::new();
+        for x in 0..10 {
+            for z in 0..10 {
+                let entity = world.create_entity().build();
+                let bounds = Aabb::from_center(Vec3::new(x as f32 * 2.0, 0.0, z as f32 * 2.0), Vec3::splat(0.5));
+                index.update(entity, bounds);
+                cubes.push((entity, bounds));
+            }
+        }
+        index.rebuild();
+        cubes
+    }
+
+    /// Every node fits its children exactly and every item points at the leaf holding it.
+    fn assert_fitted(index: &SpatialIndex) {
+        for (node, Node { bounds, kind, .. }) in index.nodes.iter().enumerate() {
+            let fitted = match kind {
+                NodeKind::Leaf(ids) => {
+                    assert!(ids.iter().all(|id| index.items[id].leaf == node));
+                    ids.iter().fold(Aabb::EMPTY, |fitted, id| fitted.union(&index.items[id].bounds))
+                },
+                NodeKind::Branch(left, right) => {
+                    assert_eq!(index.nodes[*left].parent, Some(node));
+                    assert_eq!(index.nodes[*right].parent, Some(node));
+                    index.nodes[*left].bounds.union(&index.nodes[*right].bounds)
+                },
+            };
+            assert_eq!(*bounds, fitted);
+        }
+    }
+
+    fn sorted(mut entities: Vec<Entity>) -> Vec<Entity> {
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn queries_find_inserted_entities() {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new();
+        let cubes = grid(&mut world, &mut index);
+        assert_eq!(index.len(), 100);
+        assert_eq!(index.bounds(), Aabb::new(Vec3::splat(-0.5), Vec3::new(18.5, 0.5, 18.5)));
+
+        let area = Aabb::new(Vec3::new(1.0, -1.0, 1.0), Vec3::new(5.0, 1.0, 3.0));
+        let expected: Vec<Entity> = cubes.iter().filter(|(_, bounds)| bounds.intersects(&area)).map(|(entity, _)| *entity).collect();
+        assert_eq!(expected.len(), 2);
+        assert_eq!(sorted(index.query_aabb(&area)), sorted(expected));
+        assert_eq!(index.query_sphere(Vec3::new(4.0, 0.0, 4.0), 0.1), vec![cubes[22].0]);
+    }
+
+    #[test]
+    fn removed_and_moved_entities_leave_their_old_place() {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new();
+        let cubes = grid(&mut world, &mut index);
+        let (removed, removed_bounds) = cubes[0];
+        let (moved, moved_bounds) = cubes[99];
+
+        let nodes = index.nodes.len();
+        index.remove(removed);
+        index.update(moved, Aabb::from_center(Vec3::new(-10.0, 0.0, 0.0), Vec3::splat(0.5)));
+
+        // refitted in place rather than rebuilt
+        assert_eq!(index.nodes.len(), nodes);
+        assert_fitted(&index);
+        assert_eq!(index.len(), 99);
+        assert!(index.get_bounds(removed).is_none());
+        assert!(index.query_aabb(&removed_bounds).is_empty());
+        assert!(index.query_aabb(&moved_bounds).is_empty());
+        assert_eq!(index.query_sphere(Vec3::new(-10.0, 0.0, 0.0), 1.0), vec![moved]);
+    }
+
+    #[test]
+    fn removing_everything_empties_the_tree() {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new();
+        let cubes = grid(&mut world, &mut index);
+        for (entity, _) in cubes.iter() {
+            index.remove(*entity);
+        }
+        assert_fitted(&index);
+
+        assert!(index.is_empty());
+        assert!(index.query_aabb(&Aabb::new(Vec3::splat(-100.0), Vec3::splat(100.0))).is_empty());
+        assert!(index.nearest(Vec3::ZERO, 3).is_empty());
+    }
+
+    #[test]
+    fn frustum_query_matches_testing_every_entity() {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new();
+        let cubes = grid(&mut world, &mut index);
+
+        // behind the grid's corner looking at its middle, the far corner is out of reach
+        let view = Mat4::look_at_rh(Vec3::new(-8.0, 4.0, -8.0), Vec3::new(9.0, 0.0, 9.0), Vec3::Y);
+        let projection = Camera::OPENGL_TO_WGPU_MATRIX * Mat4::perspective_rh(0.8, 1.5, 0.1, 20.0);
+        let frustum = Frustum::from_view_proj(&(projection * view));
+
+        let expected: HashSet<Entity> = cubes.iter().filter(|(_, bounds)| frustum.intersects_aabb(bounds)).map(|(entity, _)| *entity).collect();
+        let found: HashSet<Entity> = index.query_frustum(&frustum).into_iter().collect();
+        assert_eq!(found, expected);
+        assert!(found.contains(&cubes[0].0));
+        assert!(!found.contains(&cubes[99].0));
+        assert!(found.len() < cubes.len());
+    }
+
+    #[test]
+    fn nearest_and_raycast_are_closest_first() {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new();
+        let cubes = grid(&mut world, &mut index);
+
+        let nearest = index.nearest(Vec3::new(-3.0, 0.0, 0.0), 2);
+        assert_eq!(nearest[0], (cubes[0].0, 2.5));
+        assert_eq!(nearest[1].1, 2.5_f32.hypot(1.5));
+
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X);
+        let hits = index.raycast(&ray, 100.0);
+        assert_eq!(hits.len(), 10);
+        assert!(hits.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(index.raycast_first(&ray, 100.0), Some((cubes[0].0, 4.5)));
+        assert_eq!(index.raycast_first(&ray, 4.0), None);
+    }
+
+    #[test]
+    fn inserting_one_by_one_splits_leaves_and_stays_fitted() {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new();
+        let mut cubes = Vec::new();
+        for x in 0..10 {
+            for z in 0..10 {
+                let entity = world.create_entity().build();
+                let bounds = Aabb::from_center(Vec3::new(x as f32 * 2.0, 0.0, z as f32 * 2.0), Vec3::splat(0.5));
+                index.update(entity, bounds);
+                cubes.push((entity, bounds));
+            }
+        }
+        assert_fitted(&index);
+        assert!(index.nodes.len() > 1);
+
+        for (i, (entity, bounds)) in cubes.iter_mut().enumerate() {
+            if i % 3 == 0 {
+                index.remove(*entity);
+            } else if i % 3 == 1 {
+                *bounds = Aabb::from_center(bounds.center() + Vec3::new(1.0, 3.0, -1.0), Vec3::splat(0.5));
+                index.update(*entity, *bounds);
+            }
+        }
+        assert_fitted(&index);
+        assert_eq!(index.len(), 66);
+
+        let area = Aabb::new(Vec3::new(2.0, -1.0, 2.0), Vec3::new(9.0, 4.0, 9.0));
+        let expected: Vec<Entity> = cubes.iter().enumerate()
+            .filter(|(i, (_, bounds))| i % 3 != 0 && bounds.intersects(&area))
+            .map(|(_, (entity, _))| *entity)
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(sorted(index.query_aabb(&area)), sorted(expected));
+    }
+
+    #[test]
+    fn sync_only_updates_changed_entities() {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<Sprite>();
+        let cubes: Vec<Entity> = (0..8)
+            .map(|i| world.create_entity().with(Transform::new(i as f32 * 2.0, 0.0, 0.0)).with(Sprite::default()).build())
+            .collect();
+        let bare = world.create_entity().with(Transform::new(0.0, 5.0, 0.0)).build();
+
+        let lookups = Cell::new(0);
+        let unit_cube = |_: &Sprite| {
+            lookups.set(lookups.get() + 1);
+            Some(Aabb::from_center(Vec3::ZERO, Vec3::splat(0.5)))
+        };
+        let mut index = SpatialIndex::new();
+        index.sync_with(&world, unit_cube);
+        assert_eq!(index.len(), 8);
+        assert_eq!(lookups.get(), 8);
+
+        // untouched frames don't look anything up
+        index.sync_with(&world, unit_cube);
+        assert_eq!(lookups.get(), 8);
+
+        world.write_storage::<Transform>().get_mut(cubes[3]).unwrap().position = Vec3::new(0.0, 0.0, 10.0);
+        world.delete_entity(cubes[5]).unwrap();
+        world.write_storage::<Sprite>().insert(bare, Sprite::default()).unwrap();
+        world.maintain();
+        index.sync_with(&world, unit_cube);
+
+        assert_eq!(lookups.get(), 10);
+        assert_fitted(&index);
+        assert_eq!(index.len(), 8);
+        assert!(index.get_bounds(cubes[5]).is_none());
+        assert_eq!(index.query_sphere(Vec3::new(0.0, 0.0, 10.0), 0.1), vec![cubes[3]]);
+        assert!(index.query_sphere(Vec3::new(6.0, 0.0, 0.0), 0.1).is_empty());
+        assert_eq!(index.query_sphere(Vec3::new(0.0, 5.0, 0.0), 0.1), vec![bare]);
+    }
+}
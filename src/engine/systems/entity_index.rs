@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use specs::{
+    System, ReadStorage, Write, Entities, Entity, World, WorldExt, ReaderId,
+    storage::ComponentEvent,
+};
+
+use crate::components::{Name, Tags};
+
+/// Maps names and tags to entities.
+/// Kept in sync with the `Name` and `Tags` storages by the `EntityIndexSystem`,
+/// so entities spawned this frame show up after the next dispatch.
+#[derive(Debug, Default)]
+pub struct EntityIndex {
+    /// every holder of a name, newest last
+    names: HashMap<String, Vec<Entity>>,
+    tags: HashMap<String, Vec<Entity>>,
+    // what each entity was last indexed under, needed once its components are gone
+    entity_names: HashMap<u32, (Entity, String)>,
+    entity_tags: HashMap<u32, (Entity, Vec<String>)>,
+}
+
+impl EntityIndex {
+    /// The newest entity with the name when several share it.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.names.get(name).and_then(|entities| entities.last()).copied()
+    }
+
+    pub fn find_by_tag(&self, tag: &str) -> &[Entity] {
+        match self.tags.get(tag) {
+            Some(entities) => entities,
+            None => &[],
+        }
+    }
+
+    pub fn find_first_by_tag(&self, tag: &str) -> Option<Entity> {
+        self.find_by_tag(tag).first().copied()
+    }
+
+    pub fn name_of(&self, entity: Entity) -> Option<&str> {
+        match self.entity_names.get(&entity.id()) {
+            Some((indexed, name)) if *indexed == entity => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn has_tag(&self, entity: Entity, tag: &str) -> bool {
+        self.find_by_tag(tag).contains(&entity)
+    }
+
+    fn insert_name(&mut self, entity: Entity, name: &str) {
+        self.remove_name(entity.id());
+        let holders = self.names.entry(String::from(name)).or_default();
+        if !holders.is_empty() {
+            eprintln!("EntityIndex : name {:?} is used by more than one entity, lookups return the newest", name);
+        }
+        holders.push(entity);
+        self.entity_names.insert(entity.id(), (entity, String::from(name)));
+    }
+
+    fn remove_name(&mut self, id: u32) {
+        if let Some((entity, name)) = self.entity_names.remove(&id) {
+            if let Some(holders) = self.names.get_mut(&name) {
+                holders.retain(|holder| *holder != entity);
+                if holders.is_empty() {
+                    self.names.remove(&name);
+                }
+            }
+        }
+    }
+
+    fn insert_tags(&mut self, entity: Entity, tags: &Tags) {
+        self.remove_tags(entity.id());
+        let tags: Vec<String> = tags.0.iter().cloned().collect();
+        for tag in tags.iter() {
+            self.tags.entry(tag.clone()).or_default().push(entity);
+        }
+        self.entity_tags.insert(entity.id(), (entity, tags));
+    }
+
+    fn remove_tags(&mut self, id: u32) {
+        if let Some((entity, tags)) = self.entity_tags.remove(&id) {
+            for tag in tags {
+                if let Some(entities) = self.tags.get_mut(&tag) {
+                    entities.retain(|e| *e != entity);
+                    if entities.is_empty() {
+                        self.tags.remove(&tag);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct EntityIndexSystem {
+    name_reader: ReaderId<ComponentEvent>,
+    tag_reader: ReaderId<ComponentEvent>,
+}
+
+impl EntityIndexSystem {
+    /// Must be created before any named or tagged entities are built,
+    /// otherwise their insert events are missed.
+    pub fn new(world: &mut World) -> Self {
+        world.register::<Name>();
+        world.register::<Tags>();
+        let name_reader = world.write_storage::<Name>().register_reader();
+        let tag_reader = world.write_storage::<Tags>().register_reader();
+        EntityIndexSystem { name_reader, tag_reader }
+    }
+}
+
+impl<'a> System<'a> for EntityIndexSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Tags>,
+        Write<'a, EntityIndex>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, names, tags, mut index) = data;
+
+        for event in names.channel().read(&mut self.name_reader) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    let entity = entities.entity(*id);
+                    match names.get(entity) {
+                        Some(name) => index.insert_name(entity, name.as_str()),
+                        None => index.remove_name(*id),
+                    }
+                },
+                ComponentEvent::Removed(id) => index.remove_name(*id),
+            }
+        }
+
+        for event in tags.channel().read(&mut self.tag_reader) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    let entity = entities.entity(*id);
+                    match tags.get(entity) {
+                        Some(entity_tags) => index.insert_tags(entity, entity_tags),
+                        None => index.remove_tags(*id),
+                    }
+                },
+                ComponentEvent::Removed(id) => index.remove_tags(*id),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, RunNow};
+
+    use super::*;
+
+    fn setup() -> (World, EntityIndexSystem) {
+        let mut world = World::new();
+        let system = EntityIndexSystem::new(&mut world);
+        world.insert(EntityIndex::default());
+        (world, system)
+    }
+
+    fn update(world: &mut World, system: &mut EntityIndexSystem) {
+        system.run_now(world);
+        world.maintain();
+    }
+
+    #[test]
+    fn finds_entities_by_name_and_tag() {
+        let (mut world, mut system) = setup();
+        let player = world.create_entity().with(Name::new("player")).with(Tags::new(&["hero", "alive"])).build();
+        let enemy = world.create_entity().with(Tags::new(&["alive"])).build();
+        update(&mut world, &mut system);
+
+        let index = world.read_resource::<EntityIndex>();
+        assert_eq!(index.find_by_name("player"), Some(player));
+        assert_eq!(index.find_by_name("enemy"), None);
+        assert_eq!(index.name_of(player), Some("player"));
+        assert_eq!(index.find_by_tag("alive"), &[player, enemy]);
+        assert_eq!(index.find_first_by_tag("hero"), Some(player));
+        assert!(index.has_tag(enemy, "alive"));
+        assert!(!index.has_tag(enemy, "hero"));
+        assert!(index.find_by_tag("missing").is_empty());
+    }
+
+    #[test]
+    fn follows_renames_and_tag_changes() {
+        let (mut world, mut system) = setup();
+        let entity = world.create_entity().with(Name::new("before")).with(Tags::new(&["old"])).build();
+        update(&mut world, &mut system);
+
+        world.write_storage::<Name>().get_mut(entity).unwrap().0 = String::from("after");
+        let mut tags = world.write_storage::<Tags>();
+        let entity_tags = tags.get_mut(entity).unwrap();
+        entity_tags.remove("old");
+        entity_tags.add("new");
+        drop(tags);
+        update(&mut world, &mut system);
+
+        let index = world.read_resource::<EntityIndex>();
+        assert_eq!(index.find_by_name("before"), None);
+        assert_eq!(index.find_by_name("after"), Some(entity));
+        assert!(index.find_by_tag("old").is_empty());
+        assert_eq!(index.find_by_tag("new"), &[entity]);
+    }
+
+    #[test]
+    fn forgets_removed_components_and_deleted_entities() {
+        let (mut world, mut system) = setup();
+        let unnamed = world.create_entity().with(Name::new("unnamed")).with(Tags::new(&["tagged"])).build();
+        let deleted = world.create_entity().with(Name::new("deleted")).with(Tags::new(&["tagged"])).build();
+        update(&mut world, &mut system);
+
+        world.write_storage::<Name>().remove(unnamed);
+        world.delete_entity(deleted).unwrap();
+        update(&mut world, &mut system);
+
+        let index = world.read_resource::<EntityIndex>();
+        assert_eq!(index.find_by_name("unnamed"), None);
+        assert_eq!(index.find_by_name("deleted"), None);
+        assert_eq!(index.find_by_tag("tagged"), &[unnamed]);
+        assert_eq!(index.name_of(deleted), None);
+    }
+
+    #[test]
+    fn reused_ids_do_not_inherit_names() {
+        let (mut world, mut system) = setup();
+        let deleted = world.create_entity().with(Name::new("first")).build();
+        update(&mut world, &mut system);
+        world.delete_entity(deleted).unwrap();
+        world.maintain();
+
+        let reused = world.create_entity().build();
+        update(&mut world, &mut system);
+
+        assert_eq!(reused.id(), deleted.id());
+        let index = world.read_resource::<EntityIndex>();
+        assert_eq!(index.name_of(reused), None);
+        assert_eq!(index.find_by_name("first"), None);
+    }
+
+    #[test]
+    fn duplicate_names_find_the_newest() {
+        let (mut world, mut system) = setup();
+        world.create_entity().with(Name::new("twin")).build();
+        let newest = world.create_entity().with(Name::new("twin")).build();
+        update(&mut world, &mut system);
+
+        assert_eq!(world.read_resource::<EntityIndex>().find_by_name("twin"), Some(newest));
+    }
+
+    #[test]
+    fn shared_names_fall_back_to_the_remaining_holder() {
+        let (mut world, mut system) = setup();
+        let oldest = world.create_entity().with(Name::new("twin")).build();
+        let newest = world.create_entity().with(Name::new("twin")).build();
+        update(&mut world, &mut system);
+
+        world.delete_entity(newest).unwrap();
+        update(&mut world, &mut system);
+        assert_eq!(world.read_resource::<EntityIndex>().find_by_name("twin"), Some(oldest));
+
+        world.write_storage::<Name>().get_mut(oldest).unwrap().0 = String::from("single");
+        update(&mut world, &mut system);
+        let index = world.read_resource::<EntityIndex>();
+        assert_eq!(index.find_by_name("twin"), None);
+        assert_eq!(index.find_by_name("single"), Some(oldest));
+    }
+}
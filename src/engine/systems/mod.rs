@@ -1,4 +1,12 @@
 
 
 mod camera_controller;
-pub use camera_controller::CameraController as CameraController;
\ No newline at end of file
+pub use camera_controller::CameraController as CameraController;
+
+mod tween;
+pub use tween::TweenSystem as TweenSystem;
+pub use tween::TweenPropertySystem as TweenPropertySystem;
+
+mod entity_index;
+pub use entity_index::EntityIndex as EntityIndex;
+pub use entity_index::EntityIndexSystem as EntityIndexSystem;
@@ -0,0 +1,121 @@
+use std::marker::PhantomData;
+
+use specs::{System, WriteStorage, ReadStorage, Read, Write, Join, Entities, Component, shrev::EventChannel};
+
+use crate::{
+    components::{
+        Transform, Light,
+        tween::{Tweener, TweenProperty, TweenValue, TweenStatus, TweenEvent, TweenEventKind, Tweenable},
+    },
+    game::Time,
+};
+
+/// Advances every `Tweener` and writes the results into `Transform`, `Light`
+/// and the tweener's custom value map.
+pub struct TweenSystem;
+
+impl<'a> System<'a> for TweenSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        WriteStorage<'a, Tweener>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Light>,
+        Write<'a, EventChannel<TweenEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, time, mut tweeners, mut transforms, mut lights, mut events) = data;
+
+        for (entity, tweener) in (&entities, &mut tweeners).join() {
+            if tweener.paused {
+                continue;
+            }
+            let values = &mut tweener.values;
+
+            for sequence in tweener.sequences.iter_mut() {
+                if sequence.is_finished() {
+                    continue;
+                }
+                let sequence_label = sequence.label.clone();
+                let tween = sequence.current().unwrap();
+                let status = tween.step(time.delta);
+                if status == TweenStatus::Waiting {
+                    continue;
+                }
+
+                match (&tween.property, tween.value()) {
+                    (TweenProperty::Position, Some(TweenValue::Vec3(v))) => {
+                        if let Some(transform) = transforms.get_mut(entity) { transform.position = v; }
+                    },
+                    (TweenProperty::Rotation, Some(TweenValue::Quat(q))) => {
+                        if let Some(transform) = transforms.get_mut(entity) { transform.rotation = q; }
+                    },
+                    (TweenProperty::Scale, Some(TweenValue::Vec3(v))) => {
+                        if let Some(transform) = transforms.get_mut(entity) { transform.scale = v; }
+                    },
+                    (TweenProperty::LightColor, Some(TweenValue::Color(c))) => {
+                        if let Some(light) = lights.get_mut(entity) { light.color = c; }
+                    },
+                    (TweenProperty::LightIntensity, Some(TweenValue::Float(f))) => {
+                        if let Some(light) = lights.get_mut(entity) { light.intensity = f; }
+                    },
+                    (TweenProperty::Custom(name), Some(value)) => {
+                        values.insert(name.clone(), value);
+                    },
+                    // mismatched tweens were dropped when their sequence was made
+                    _ => {}
+                }
+
+                match status {
+                    TweenStatus::LoopCompleted => {
+                        events.single_write(TweenEvent { entity, label: tween.label.clone(), kind: TweenEventKind::LoopCompleted });
+                    },
+                    TweenStatus::Completed => {
+                        events.single_write(TweenEvent { entity, label: tween.label.clone(), kind: TweenEventKind::Completed });
+                        sequence.advance();
+                        if sequence.is_finished() {
+                            events.single_write(TweenEvent { entity, label: sequence_label, kind: TweenEventKind::SequenceCompleted });
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Copies custom tween values from each entity's `Tweener` into component `T`.
+/// Add one to the dispatcher after `TweenSystem` for every `Tweenable` component.
+pub struct TweenPropertySystem<T> {
+    marker: PhantomData<T>,
+}
+
+impl<T> TweenPropertySystem<T> {
+    pub fn new() -> Self {
+        TweenPropertySystem { marker: PhantomData }
+    }
+}
+
+impl<T> Default for TweenPropertySystem<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> System<'a> for TweenPropertySystem<T> where T: Component + Tweenable + Send + Sync {
+    type SystemData = (
+        ReadStorage<'a, Tweener>,
+        WriteStorage<'a, T>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (tweeners, mut targets) = data;
+
+        for (tweener, target) in (&tweeners, &mut targets).join() {
+            for (property, value) in tweener.values.iter() {
+                target.set_tween_value(property, value);
+            }
+        }
+    }
+}
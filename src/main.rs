@@ -1,4 +1,4 @@
-use std::{sync::Arc, cell::RefCell};
+use glam::Vec3;
 
 use winit::{
     event_loop::{EventLoop},
@@ -14,8 +14,9 @@ use wvreng::{
         Builder, DispatcherBuilder, WorldExt,
     },
     components::{
-        Transform, ModelRenderer, Camera, transform::EulerRotation, Sprite, Light,
-    }, systems::CameraController, assets::{AssetManager, asset_manager::{AssetType, AssetRef}}, graphics::{util::MeshPrimitives, Material, Color},
+        Transform, ModelRenderer, Camera, Sprite, Light, Tweener, Tween, Name, Tags,
+        tween::{Easing, TweenLoop},
+    }, systems::{CameraController, TweenSystem, EntityIndexSystem}, assets::asset_manager::{AssetType, AssetRef}, graphics::{util::MeshPrimitives, Material, AlphaMode, Color},
 };
 
 
@@ -31,6 +32,10 @@ fn main() {
     game.world.register::<Camera>();
     game.world.register::<ModelRenderer>();
     game.world.register::<Light>();
+    game.world.register::<Tweener>();
+    game.world.register::<Name>();
+    game.world.register::<Tags>();
+    let entity_index_system = EntityIndexSystem::new(&mut game.world);
 
     let wall_texture = 
         pollster::block_on(game.asset_manager.load_texture(&game.renderer.get_graphics_bundle(), "download.png")).unwrap();
@@ -39,13 +44,25 @@ fn main() {
         pollster::block_on(game.asset_manager.load_texture(&game.renderer.get_graphics_bundle(), "light-icon.png")).unwrap();
     
     
+    let mut wall_material = Material::new(AssetRef::new(None, Some("Universal".to_string()), AssetType::RenderPipeline), "wall_material")
+        .with_base_color_texture(wall_texture)
+        .with_metallic_roughness(0.0, 0.8);
+    // surface detail for the wall when a normal map ships next to its texture
+    if let Ok(wall_normal) = pollster::block_on(game.asset_manager.load_linear_texture(&game.renderer.get_graphics_bundle(), "download-normal.png")) {
+        wall_material = wall_material.with_normal_texture(wall_normal, 1.0);
+    }
+    let wall_material = wall_material.build(&game.renderer.get_graphics_bundle(), &game.asset_manager).unwrap();
     let wall_material = game.asset_manager.insert_asset(
-        Material::new(AssetRef::new(None, Some("Universal".to_string()), AssetType::RenderPipeline), wall_texture, "wall_material"),
+        wall_material,
         AssetType::Material, 
         "wall_material"
     );
+    let light_icon_mat = Material::new(AssetRef::new(None, Some("Debug Icon".to_string()), AssetType::RenderPipeline), "light_icon")
+        .with_base_color_texture(light_icon)
+        .with_alpha_mode(AlphaMode::Blend)
+        .build(&game.renderer.get_graphics_bundle(), &game.asset_manager).unwrap();
     let light_icon_mat = game.asset_manager.insert_asset(
-        Material::new(AssetRef::new(None, Some("Debug Icon".to_string()), AssetType::RenderPipeline), light_icon, "light_icon"),
+        light_icon_mat,
         AssetType::Material, 
         "light_icon_mat"
     );
@@ -61,13 +78,20 @@ fn main() {
             game.world.create_entity()
             .with(Transform::new(x as f32 * 2.0 - 5.0, y as f32 / 2.0, y as f32 * 2.0 - 5.0))
             .with(Sprite::new(wall_material.clone(), plane.clone()))
+            .with(Tags::new(&["wall"]))
             .build();
         }
     }
     game.world.create_entity()
         .with(Transform::new(0.0, 2.0, 0.0))
         .with(Sprite::new(light_icon_mat.clone(), plane.clone()))
-        .with(Light::new(&game.renderer.device, Color::BLUE, 5.0))
+        .with(Light::new(Color::BLUE, 5.0))
+        .with(Name::new("Light"))
+        .with(Tweener::new().with(
+            Tween::position(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 3.0, 0.0), 2.0)
+                .with_easing(Easing::SineInOut)
+                .with_loop(TweenLoop::PingPong)
+        ))
         .build();
     
     // camera
@@ -80,6 +104,7 @@ fn main() {
     game.world.create_entity()
         .with(camera)
         .with(Transform::new(0.0, 0.0, 0.0))
+        .with(Name::new("Main Camera"))
         .build();
 
     game.init_internal_resources(&window);
@@ -90,8 +115,10 @@ fn main() {
 
 
     let dispatcher = dispatcher_builder
+        .with(entity_index_system, "entity_index", &[])
         //.with(TransformSystem, "transform_system", &[])
         .with(CameraController, "camera_controller", &[])
+        .with(TweenSystem, "tween_system", &[])
         .build();
     
     println!("Game loaded in {:?} milliseconds!", chrono::Local::now().timestamp_millis() - _start_time);